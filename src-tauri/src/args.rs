@@ -0,0 +1,677 @@
+use std::str::FromStr;
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq)]
+pub enum SubFormat {
+    #[serde(rename = "SRT")]
+    Srt,
+    #[serde(rename = "VTT")]
+    Vtt,
+}
+
+impl FromStr for SubFormat {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value.to_uppercase().as_str() {
+            "SRT" => Ok(SubFormat::Srt),
+            "VTT" => Ok(SubFormat::Vtt),
+            _ => Err(format!("Unknown subtitle format: {}", value)),
+        }
+    }
+}
+
+impl SubFormat {
+    fn as_arg(&self) -> &'static str {
+        match self {
+            SubFormat::Srt => "SRT",
+            SubFormat::Vtt => "VTT",
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "UPPERCASE")]
+pub enum LogLevel {
+    Debug,
+    Info,
+    Warn,
+    Error,
+    Off,
+}
+
+impl FromStr for LogLevel {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value.to_uppercase().as_str() {
+            "DEBUG" => Ok(LogLevel::Debug),
+            "INFO" => Ok(LogLevel::Info),
+            "WARN" => Ok(LogLevel::Warn),
+            "ERROR" => Ok(LogLevel::Error),
+            "OFF" => Ok(LogLevel::Off),
+            _ => Err(format!("Unknown log level: {}", value)),
+        }
+    }
+}
+
+impl LogLevel {
+    fn as_arg(&self) -> &'static str {
+        match self {
+            LogLevel::Debug => "DEBUG",
+            LogLevel::Info => "INFO",
+            LogLevel::Warn => "WARN",
+            LogLevel::Error => "ERROR",
+            LogLevel::Off => "OFF",
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum DecryptionEngine {
+    Ffmpeg,
+    Mp4decrypt,
+    ShakaPackager,
+}
+
+impl DecryptionEngine {
+    fn as_arg(&self) -> &'static str {
+        match self {
+            DecryptionEngine::Ffmpeg => "FFMPEG",
+            DecryptionEngine::Mp4decrypt => "MP4DECRYPT",
+            DecryptionEngine::ShakaPackager => "SHAKA_PACKAGER",
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum MuxFormat {
+    Mkv,
+    Mp4,
+    Ts,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum Muxer {
+    Ffmpeg,
+    Mkvmerge,
+}
+
+// Value of `-M`/`--mux-after-done`
+#[derive(Debug, Clone, PartialEq)]
+pub struct MuxOptions {
+    pub format: MuxFormat,
+    pub muxer: Muxer,
+    pub bin_path: String,
+    pub skip_sub: bool,
+    pub keep: bool,
+}
+
+impl Default for MuxOptions {
+    fn default() -> Self {
+        MuxOptions {
+            format: MuxFormat::Mkv,
+            muxer: Muxer::Ffmpeg,
+            bin_path: "auto".to_string(),
+            skip_sub: false,
+            keep: true,
+        }
+    }
+}
+
+impl MuxOptions {
+    fn as_arg(&self) -> String {
+        let format = match self.format {
+            MuxFormat::Mkv => "mkv",
+            MuxFormat::Mp4 => "mp4",
+            MuxFormat::Ts => "ts",
+        };
+        let muxer = match self.muxer {
+            Muxer::Ffmpeg => "ffmpeg",
+            Muxer::Mkvmerge => "mkvmerge",
+        };
+        format!("format={}:muxer={}:bin_path={}:skip_sub={}:keep={}",
+            format, muxer, self.bin_path, self.skip_sub, self.keep)
+    }
+}
+
+// Typed command line of N_m3u8DL-RE. Every option maps to one flag; `None`
+// (or `false` for switches) leaves the tool's own default in place.
+#[allow(non_camel_case_types)]
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct N_m3u8DLArgs {
+    pub input: String,
+
+    // Naming and locations
+    pub save_name: Option<String>,
+    pub save_dir: Option<String>,
+    pub tmp_dir: Option<String>,
+    pub log_file_path: Option<String>,
+    pub base_url: Option<String>,
+
+    // Network
+    pub headers: Vec<(String, String)>,
+    pub thread_count: Option<u32>,
+    pub download_retry_count: Option<u32>,
+    pub http_request_timeout: Option<u32>,
+    pub use_system_proxy: Option<bool>,
+    pub custom_proxy: Option<String>,
+    pub custom_range: Option<String>,
+    pub append_url_params: bool,
+    pub concurrent_download: bool,
+    pub ad_keywords: Vec<String>,
+    pub allow_hls_multi_ext_map: bool,
+
+    // Stream selection
+    pub auto_select: bool,
+    pub select_video: Option<String>,
+    pub select_audio: Option<String>,
+    pub select_subtitle: Option<String>,
+    pub drop_video: Option<String>,
+    pub drop_audio: Option<String>,
+    pub drop_subtitle: Option<String>,
+    pub sub_only: bool,
+
+    // Download and merge behaviour
+    pub skip_merge: bool,
+    pub skip_download: bool,
+    pub check_segments_count: Option<bool>,
+    pub binary_merge: bool,
+    pub use_ffmpeg_concat_demuxer: bool,
+    pub del_after_done: Option<bool>,
+    pub write_meta_json: Option<bool>,
+    pub no_date_info: bool,
+    pub sub_format: Option<SubFormat>,
+    pub auto_subtitle_fix: Option<bool>,
+    pub ffmpeg_binary_path: Option<String>,
+
+    // Muxing
+    pub mux_after_done: Option<MuxOptions>,
+    pub mux_imports: Vec<String>,
+
+    // Decryption
+    pub keys: Vec<String>,
+    pub key_text_file: Option<String>,
+    pub decryption_engine: Option<DecryptionEngine>,
+    pub decryption_binary_path: Option<String>,
+    pub mp4_real_time_decryption: bool,
+    pub custom_hls_method: Option<String>,
+    pub custom_hls_key: Option<String>,
+    pub custom_hls_iv: Option<String>,
+
+    // Live streams
+    pub live_perform_as_vod: bool,
+    pub live_real_time_merge: bool,
+    pub live_keep_segments: Option<bool>,
+    pub live_pipe_mux: bool,
+    pub live_fix_vtt_by_audio: bool,
+    pub live_record_limit: Option<String>,
+    pub live_wait_time: Option<u32>,
+    pub live_take_count: Option<u32>,
+    pub task_start_at: Option<String>,
+
+    // Console and logging
+    pub log_level: Option<LogLevel>,
+    pub no_log: bool,
+    pub force_ansi_console: bool,
+    pub no_ansi_color: bool,
+    pub ui_language: Option<String>,
+    pub disable_update_check: bool,
+}
+
+// Generates chainable setters: `string` and `value` ones wrap in `Some`,
+// `switch` ones turn a flag on
+macro_rules! setters {
+    (string $($name:ident),* $(,)?) => {
+        $(pub fn $name(mut self, value: impl Into<String>) -> Self {
+            self.$name = Some(value.into());
+            self
+        })*
+    };
+    (value $($name:ident: $ty:ty),* $(,)?) => {
+        $(pub fn $name(mut self, value: $ty) -> Self {
+            self.$name = Some(value);
+            self
+        })*
+    };
+    (switch $($name:ident),* $(,)?) => {
+        $(pub fn $name(mut self, enabled: bool) -> Self {
+            self.$name = enabled;
+            self
+        })*
+    };
+}
+
+impl N_m3u8DLArgs {
+    pub fn new(input: impl Into<String>) -> Self {
+        N_m3u8DLArgs {
+            input: input.into(),
+            ..N_m3u8DLArgs::default()
+        }
+    }
+
+    setters!(string
+        save_name,
+        save_dir,
+        tmp_dir,
+        select_video,
+        select_audio,
+        select_subtitle,
+    );
+
+    setters!(value
+        thread_count: u32,
+        download_retry_count: u32,
+        http_request_timeout: u32,
+        use_system_proxy: bool,
+        check_segments_count: bool,
+        del_after_done: bool,
+        write_meta_json: bool,
+        sub_format: SubFormat,
+        auto_subtitle_fix: bool,
+        mux_after_done: MuxOptions,
+        log_level: LogLevel,
+    );
+
+    setters!(switch
+        append_url_params,
+        concurrent_download,
+        sub_only,
+        skip_download,
+        binary_merge,
+        use_ffmpeg_concat_demuxer,
+        no_date_info,
+        no_log,
+        force_ansi_console,
+    );
+
+    pub fn header(mut self, key: impl Into<String>, value: impl Into<String>) -> Self {
+        self.headers.push((key.into(), value.into()));
+        self
+    }
+
+    pub fn to_args(&self) -> Vec<String> {
+        let mut args = vec![self.input.clone()];
+
+        let mut value = |flag: &str, value: Option<String>| {
+            if let Some(value) = value {
+                args.push(flag.to_string());
+                args.push(value);
+            }
+        };
+        value("--save-name", self.save_name.clone());
+        value("--save-dir", self.save_dir.clone());
+        value("--tmp-dir", self.tmp_dir.clone());
+        value("--log-file-path", self.log_file_path.clone());
+        value("--base-url", self.base_url.clone());
+        for (key, header) in &self.headers {
+            value("--header", Some(format!("{}: {}", key, header)));
+        }
+        value("--thread-count", self.thread_count.map(|v| v.to_string()));
+        value("--download-retry-count", self.download_retry_count.map(|v| v.to_string()));
+        value("--http-request-timeout", self.http_request_timeout.map(|v| v.to_string()));
+        value("--use-system-proxy", self.use_system_proxy.map(|v| v.to_string()));
+        value("--custom-proxy", self.custom_proxy.clone());
+        value("--custom-range", self.custom_range.clone());
+        for keyword in &self.ad_keywords {
+            value("--ad-keyword", Some(keyword.clone()));
+        }
+        value("-sv", self.select_video.clone());
+        value("-sa", self.select_audio.clone());
+        value("-ss", self.select_subtitle.clone());
+        value("-dv", self.drop_video.clone());
+        value("-da", self.drop_audio.clone());
+        value("-ds", self.drop_subtitle.clone());
+        value("--check-segments-count", self.check_segments_count.map(|v| v.to_string()));
+        value("--del-after-done", self.del_after_done.map(|v| v.to_string()));
+        value("--write-meta-json", self.write_meta_json.map(|v| v.to_string()));
+        value("--sub-format", self.sub_format.map(|v| v.as_arg().to_string()));
+        value("--auto-subtitle-fix", self.auto_subtitle_fix.map(|v| v.to_string()));
+        value("--ffmpeg-binary-path", self.ffmpeg_binary_path.clone());
+        value("-M", self.mux_after_done.as_ref().map(MuxOptions::as_arg));
+        for import in &self.mux_imports {
+            value("--mux-import", Some(import.clone()));
+        }
+        for key in &self.keys {
+            value("--key", Some(key.clone()));
+        }
+        value("--key-text-file", self.key_text_file.clone());
+        value("--decryption-engine", self.decryption_engine.map(|v| v.as_arg().to_string()));
+        value("--decryption-binary-path", self.decryption_binary_path.clone());
+        value("--custom-hls-method", self.custom_hls_method.clone());
+        value("--custom-hls-key", self.custom_hls_key.clone());
+        value("--custom-hls-iv", self.custom_hls_iv.clone());
+        value("--live-keep-segments", self.live_keep_segments.map(|v| v.to_string()));
+        value("--live-record-limit", self.live_record_limit.clone());
+        value("--live-wait-time", self.live_wait_time.map(|v| v.to_string()));
+        value("--live-take-count", self.live_take_count.map(|v| v.to_string()));
+        value("--task-start-at", self.task_start_at.clone());
+        value("--log-level", self.log_level.map(|v| v.as_arg().to_string()));
+        value("--ui-language", self.ui_language.clone());
+
+        let switches = [
+            ("--append-url-params", self.append_url_params),
+            ("--concurrent-download", self.concurrent_download),
+            ("--allow-hls-multi-ext-map", self.allow_hls_multi_ext_map),
+            ("--auto-select", self.auto_select),
+            ("--sub-only", self.sub_only),
+            ("--skip-merge", self.skip_merge),
+            ("--skip-download", self.skip_download),
+            ("--binary-merge", self.binary_merge),
+            ("--use-ffmpeg-concat-demuxer", self.use_ffmpeg_concat_demuxer),
+            ("--no-date-info", self.no_date_info),
+            ("--mp4-real-time-decryption", self.mp4_real_time_decryption),
+            ("--live-perform-as-vod", self.live_perform_as_vod),
+            ("--live-real-time-merge", self.live_real_time_merge),
+            ("--live-pipe-mux", self.live_pipe_mux),
+            ("--live-fix-vtt-by-audio", self.live_fix_vtt_by_audio),
+            ("--no-log", self.no_log),
+            ("--force-ansi-console", self.force_ansi_console),
+            ("--no-ansi-color", self.no_ansi_color),
+            ("--disable-update-check", self.disable_update_check),
+        ];
+        for (flag, enabled) in switches {
+            if enabled {
+                args.push(flag.to_string());
+            }
+        }
+
+        args
+    }
+
+    // Command line for the user to copy, quoted for the current platform's shell
+    pub fn to_command_line(&self, binary: &str) -> String {
+        std::iter::once(binary.to_string())
+            .chain(self.to_args())
+            .map(|arg| quote_arg(&arg))
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+}
+
+// Flags the GUI doesn't set yet, kept so the builder covers the whole
+// N_m3u8DL-RE command line
+#[allow(dead_code)]
+impl N_m3u8DLArgs {
+    setters!(string
+        log_file_path,
+        base_url,
+        custom_proxy,
+        custom_range,
+        drop_video,
+        drop_audio,
+        drop_subtitle,
+        ffmpeg_binary_path,
+        key_text_file,
+        decryption_binary_path,
+        custom_hls_method,
+        custom_hls_key,
+        custom_hls_iv,
+        live_record_limit,
+        task_start_at,
+        ui_language,
+    );
+
+    setters!(value
+        decryption_engine: DecryptionEngine,
+        live_keep_segments: bool,
+        live_wait_time: u32,
+        live_take_count: u32,
+    );
+
+    setters!(switch
+        allow_hls_multi_ext_map,
+        auto_select,
+        skip_merge,
+        mp4_real_time_decryption,
+        live_perform_as_vod,
+        live_real_time_merge,
+        live_pipe_mux,
+        live_fix_vtt_by_audio,
+        no_ansi_color,
+        disable_update_check,
+    );
+
+    pub fn key(mut self, key: impl Into<String>) -> Self {
+        self.keys.push(key.into());
+        self
+    }
+
+    pub fn mux_import(mut self, options: impl Into<String>) -> Self {
+        self.mux_imports.push(options.into());
+        self
+    }
+
+    pub fn ad_keyword(mut self, pattern: impl Into<String>) -> Self {
+        self.ad_keywords.push(pattern.into());
+        self
+    }
+}
+
+fn quote_arg(arg: &str) -> String {
+    let plain = !arg.is_empty() && arg.chars().all(|c| c.is_ascii_alphanumeric() || "-_./:=,+@%".contains(c));
+    if plain {
+        arg.to_string()
+    } else if cfg!(target_os = "windows") {
+        format!("\"{}\"", arg.replace('"', "\\\""))
+    } else {
+        format!("'{}'", arg.replace('\'', "'\\''"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn input_only() {
+        let args = N_m3u8DLArgs::new("https://example.com/master.m3u8");
+        assert_eq!(args.to_args(), ["https://example.com/master.m3u8"]);
+    }
+
+    #[test]
+    fn naming_and_locations() {
+        let args = N_m3u8DLArgs::new("in.m3u8")
+            .save_name("My Video")
+            .save_dir("/downloads")
+            .tmp_dir("/downloads/Temp")
+            .log_file_path("/logs/run.log")
+            .base_url("https://cdn.example.com/");
+        assert_eq!(args.to_args(), [
+            "in.m3u8",
+            "--save-name", "My Video",
+            "--save-dir", "/downloads",
+            "--tmp-dir", "/downloads/Temp",
+            "--log-file-path", "/logs/run.log",
+            "--base-url", "https://cdn.example.com/",
+        ]);
+    }
+
+    #[test]
+    fn headers_use_long_flag() {
+        let args = N_m3u8DLArgs::new("in.m3u8")
+            .header("User-Agent", "Mozilla/5.0")
+            .header("Cookie", "a=b; c=d");
+        assert_eq!(args.to_args(), [
+            "in.m3u8",
+            "--header", "User-Agent: Mozilla/5.0",
+            "--header", "Cookie: a=b; c=d",
+        ]);
+    }
+
+    #[test]
+    fn stream_selection() {
+        let args = N_m3u8DLArgs::new("in.m3u8")
+            .select_video("res=1920x1080:for=best")
+            .select_audio("lang=en")
+            .select_subtitle("all")
+            .drop_video("codecs=hvc1")
+            .drop_audio("lang=fr")
+            .drop_subtitle("name=forced")
+            .auto_select(true)
+            .sub_only(true);
+        assert_eq!(args.to_args(), [
+            "in.m3u8",
+            "-sv", "res=1920x1080:for=best",
+            "-sa", "lang=en",
+            "-ss", "all",
+            "-dv", "codecs=hvc1",
+            "-da", "lang=fr",
+            "-ds", "name=forced",
+            "--auto-select",
+            "--sub-only",
+        ]);
+    }
+
+    #[test]
+    fn network_options() {
+        let args = N_m3u8DLArgs::new("in.m3u8")
+            .thread_count(16)
+            .download_retry_count(3)
+            .http_request_timeout(100)
+            .use_system_proxy(false)
+            .custom_proxy("http://127.0.0.1:8888")
+            .custom_range("0-10")
+            .ad_keyword("\\d{3}.ts")
+            .append_url_params(true)
+            .concurrent_download(true);
+        assert_eq!(args.to_args(), [
+            "in.m3u8",
+            "--thread-count", "16",
+            "--download-retry-count", "3",
+            "--http-request-timeout", "100",
+            "--use-system-proxy", "false",
+            "--custom-proxy", "http://127.0.0.1:8888",
+            "--custom-range", "0-10",
+            "--ad-keyword", "\\d{3}.ts",
+            "--append-url-params",
+            "--concurrent-download",
+        ]);
+    }
+
+    #[test]
+    fn muxing() {
+        let args = N_m3u8DLArgs::new("in.m3u8")
+            .mux_after_done(MuxOptions { skip_sub: true, ..MuxOptions::default() })
+            .mux_import("path=zh.srt:lang=chi");
+        assert_eq!(args.to_args(), [
+            "in.m3u8",
+            "-M", "format=mkv:muxer=ffmpeg:bin_path=auto:skip_sub=true:keep=true",
+            "--mux-import", "path=zh.srt:lang=chi",
+        ]);
+
+        let mp4 = MuxOptions { format: MuxFormat::Mp4, muxer: Muxer::Mkvmerge, keep: false, ..MuxOptions::default() };
+        assert_eq!(mp4.as_arg(), "format=mp4:muxer=mkvmerge:bin_path=auto:skip_sub=false:keep=false");
+    }
+
+    #[test]
+    fn decryption() {
+        let args = N_m3u8DLArgs::new("in.mpd")
+            .key("kid1:key1")
+            .key("kid2:key2")
+            .key_text_file("keys.txt")
+            .decryption_engine(DecryptionEngine::ShakaPackager)
+            .decryption_binary_path("/bin/packager")
+            .custom_hls_method("AES_128")
+            .custom_hls_key("0x00112233")
+            .custom_hls_iv("0x0")
+            .mp4_real_time_decryption(true);
+        assert_eq!(args.to_args(), [
+            "in.mpd",
+            "--key", "kid1:key1",
+            "--key", "kid2:key2",
+            "--key-text-file", "keys.txt",
+            "--decryption-engine", "SHAKA_PACKAGER",
+            "--decryption-binary-path", "/bin/packager",
+            "--custom-hls-method", "AES_128",
+            "--custom-hls-key", "0x00112233",
+            "--custom-hls-iv", "0x0",
+            "--mp4-real-time-decryption",
+        ]);
+    }
+
+    #[test]
+    fn live_options() {
+        let args = N_m3u8DLArgs::new("live.m3u8")
+            .live_keep_segments(false)
+            .live_record_limit("01:00:00")
+            .live_wait_time(5)
+            .live_take_count(16)
+            .task_start_at("20240101120000")
+            .live_perform_as_vod(true)
+            .live_real_time_merge(true)
+            .live_pipe_mux(true)
+            .live_fix_vtt_by_audio(true);
+        assert_eq!(args.to_args(), [
+            "live.m3u8",
+            "--live-keep-segments", "false",
+            "--live-record-limit", "01:00:00",
+            "--live-wait-time", "5",
+            "--live-take-count", "16",
+            "--task-start-at", "20240101120000",
+            "--live-perform-as-vod",
+            "--live-real-time-merge",
+            "--live-pipe-mux",
+            "--live-fix-vtt-by-audio",
+        ]);
+    }
+
+    #[test]
+    fn download_and_console_options() {
+        let args = N_m3u8DLArgs::new("in.m3u8")
+            .check_segments_count(false)
+            .del_after_done(true)
+            .write_meta_json(false)
+            .sub_format(SubFormat::Vtt)
+            .auto_subtitle_fix(false)
+            .ffmpeg_binary_path("/bin/ffmpeg")
+            .log_level(LogLevel::Debug)
+            .ui_language("en-US")
+            .skip_merge(true)
+            .skip_download(true)
+            .binary_merge(true)
+            .use_ffmpeg_concat_demuxer(true)
+            .no_date_info(true)
+            .no_log(true)
+            .force_ansi_console(true)
+            .no_ansi_color(true)
+            .disable_update_check(true)
+            .allow_hls_multi_ext_map(true);
+        assert_eq!(args.to_args(), [
+            "in.m3u8",
+            "--check-segments-count", "false",
+            "--del-after-done", "true",
+            "--write-meta-json", "false",
+            "--sub-format", "VTT",
+            "--auto-subtitle-fix", "false",
+            "--ffmpeg-binary-path", "/bin/ffmpeg",
+            "--log-level", "DEBUG",
+            "--ui-language", "en-US",
+            "--allow-hls-multi-ext-map",
+            "--skip-merge",
+            "--skip-download",
+            "--binary-merge",
+            "--use-ffmpeg-concat-demuxer",
+            "--no-date-info",
+            "--no-log",
+            "--force-ansi-console",
+            "--no-ansi-color",
+            "--disable-update-check",
+        ]);
+    }
+
+    #[test]
+    #[cfg(not(target_os = "windows"))]
+    fn command_line_quotes_arguments() {
+        let args = N_m3u8DLArgs::new("https://example.com/a.m3u8?x=1&y=2")
+            .save_name("It's here")
+            .header("Referer", "https://example.com/");
+        assert_eq!(
+            args.to_command_line("/opt/bin/N_m3u8DL-RE"),
+            "/opt/bin/N_m3u8DL-RE 'https://example.com/a.m3u8?x=1&y=2' --save-name 'It'\\''s here' --header 'Referer: https://example.com/'"
+        );
+    }
+}
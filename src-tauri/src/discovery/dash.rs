@@ -0,0 +1,280 @@
+use roxmltree::{Document, Node};
+use url::Url;
+
+use super::{children, resolve};
+use crate::streams::{non_empty, stable_id, AudioStream, StreamInfo, SubtitleStream, VideoStream};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum StreamKind {
+    Video,
+    Audio,
+    Subtitle,
+}
+
+pub fn is_manifest(text: &str) -> bool {
+    text.contains("<MPD")
+}
+
+// Lists every Representation of every Period. Attributes missing on a
+// Representation are inherited from its AdaptationSet, BaseURLs are resolved
+// down from the MPD element.
+pub fn parse(text: &str, base: &Url) -> Result<StreamInfo, String> {
+    let document = Document::parse(text).map_err(|e| format!("Invalid MPD: {}", e))?;
+    let mpd = document.root_element();
+    if mpd.tag_name().name() != "MPD" {
+        return Err(format!("Expected an MPD element, found {}", mpd.tag_name().name()));
+    }
+
+    let mut info = StreamInfo::default();
+    let mpd_base = with_base_url(base, mpd);
+    for (period_index, period) in children(mpd, "Period").enumerate() {
+        let period_base = with_base_url(&mpd_base, period);
+        for set in children(period, "AdaptationSet") {
+            let set_base = with_base_url(&period_base, set);
+            let role = child(set, "Role").and_then(|role| role.attribute("value")).map(String::from);
+            let label = set.attribute("label")
+                .or_else(|| child(set, "Label").and_then(|label| label.text()));
+
+            for representation in children(set, "Representation") {
+                let attribute = |name| representation.attribute(name).or_else(|| set.attribute(name));
+                let Some(kind) = stream_kind(set.attribute("contentType"), attribute("mimeType"), attribute("codecs")) else {
+                    continue;
+                };
+                // N_m3u8DL-RE uses the Representation id as GroupId
+                let group_id = attribute("id").unwrap_or_default();
+                let bandwidth = attribute("bandwidth").and_then(|value| value.parse::<u64>().ok());
+                let period = period_index.to_string();
+                let identity = [period.as_str(), group_id, attribute("bandwidth").unwrap_or_default()];
+                let url = child(representation, "BaseURL")
+                    .and_then(|base_url| base_url.text())
+                    .map(|base_url| resolve(&set_base, base_url.trim()));
+                let encrypted = child(set, "ContentProtection").is_some()
+                    || child(representation, "ContentProtection").is_some();
+
+                match kind {
+                    StreamKind::Video => {
+                        let resolution = match (attribute("width"), attribute("height")) {
+                            (Some(width), Some(height)) => Some(format!("{}x{}", width, height)),
+                            _ => None,
+                        };
+                        info.video_streams.push(VideoStream {
+                            id: stable_id("video", &identity),
+                            group_id: non_empty(group_id),
+                            resolution,
+                            bandwidth,
+                            frame_rate: attribute("frameRate").and_then(parse_frame_rate),
+                            codecs: attribute("codecs").and_then(non_empty),
+                            url,
+                            period: Some(period_index),
+                            role: role.clone(),
+                            encrypted,
+                            ..VideoStream::default()
+                        });
+                    }
+                    StreamKind::Audio => {
+                        let channels = child(representation, "AudioChannelConfiguration")
+                            .or_else(|| child(set, "AudioChannelConfiguration"))
+                            .and_then(|configuration| configuration.attribute("value"));
+                        info.audio_streams.push(AudioStream {
+                            id: stable_id("audio", &identity),
+                            group_id: non_empty(group_id),
+                            name: label.and_then(non_empty),
+                            language: attribute("lang").and_then(non_empty),
+                            channels: channels.and_then(non_empty),
+                            bandwidth,
+                            codecs: attribute("codecs").and_then(non_empty),
+                            url,
+                            period: Some(period_index),
+                            role: role.clone(),
+                            encrypted,
+                            ..AudioStream::default()
+                        });
+                    }
+                    StreamKind::Subtitle => {
+                        info.subtitle_streams.push(SubtitleStream {
+                            id: stable_id("subtitle", &identity),
+                            group_id: non_empty(group_id),
+                            name: label.and_then(non_empty),
+                            language: attribute("lang").and_then(non_empty),
+                            codecs: attribute("codecs").and_then(non_empty),
+                            url,
+                            period: Some(period_index),
+                            role: role.clone(),
+                            ..SubtitleStream::default()
+                        });
+                    }
+                }
+            }
+        }
+    }
+
+    Ok(info)
+}
+
+// contentType is optional, so fall back to the MIME type and, for subtitles
+// packed into MP4, the codec
+fn stream_kind(content_type: Option<&str>, mime_type: Option<&str>, codecs: Option<&str>) -> Option<StreamKind> {
+    match content_type {
+        Some("video") => return Some(StreamKind::Video),
+        Some("audio") => return Some(StreamKind::Audio),
+        Some("text") => return Some(StreamKind::Subtitle),
+        _ => {}
+    }
+    let mime_type = mime_type?;
+    if mime_type.starts_with("video/") {
+        Some(StreamKind::Video)
+    } else if mime_type.starts_with("audio/") {
+        Some(StreamKind::Audio)
+    } else if mime_type.starts_with("text/")
+        || mime_type == "application/ttml+xml"
+        || codecs.is_some_and(|codecs| codecs.starts_with("stpp") || codecs.starts_with("wvtt"))
+    {
+        Some(StreamKind::Subtitle)
+    } else {
+        None
+    }
+}
+
+// Frame rates are either plain numbers or fractions like 30000/1001
+fn parse_frame_rate(value: &str) -> Option<f64> {
+    let rate = match value.split_once('/') {
+        Some((numerator, denominator)) => {
+            let denominator: f64 = denominator.trim().parse().ok()?;
+            if denominator == 0.0 {
+                return None;
+            }
+            numerator.trim().parse::<f64>().ok()? / denominator
+        }
+        None => value.trim().parse().ok()?,
+    };
+    Some((rate * 1000.0).round() / 1000.0)
+}
+
+fn with_base_url(base: &Url, node: Node) -> Url {
+    child(node, "BaseURL")
+        .and_then(|base_url| base_url.text())
+        .and_then(|base_url| base.join(base_url.trim()).ok())
+        .unwrap_or_else(|| base.clone())
+}
+
+fn child<'a, 'input>(node: Node<'a, 'input>, name: &'static str) -> Option<Node<'a, 'input>> {
+    children(node, name).next()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MPD: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<MPD xmlns="urn:mpeg:dash:schema:mpd:2011" xmlns:cenc="urn:mpeg:cenc:2013" type="static" mediaPresentationDuration="PT1H">
+  <BaseURL>media/</BaseURL>
+  <Period id="ad">
+    <AdaptationSet contentType="video" mimeType="video/mp4" codecs="avc1.4d401e" frameRate="25">
+      <Representation id="ad-video" bandwidth="800000" width="640" height="360">
+        <BaseURL>ad.mp4</BaseURL>
+      </Representation>
+    </AdaptationSet>
+  </Period>
+  <Period id="main">
+    <BaseURL>main/</BaseURL>
+    <AdaptationSet mimeType="video/mp4" frameRate="30000/1001">
+      <Role schemeIdUri="urn:mpeg:dash:role:2011" value="main"/>
+      <ContentProtection schemeIdUri="urn:mpeg:dash:mp4protection:2011" value="cenc" cenc:default_KID="00000000-0000-0000-0000-000000000000"/>
+      <Representation id="v1080" bandwidth="5000000" width="1920" height="1080" codecs="avc1.640028"/>
+      <Representation id="v720" bandwidth="2500000" width="1280" height="720" codecs="avc1.64001f" frameRate="60"/>
+    </AdaptationSet>
+    <AdaptationSet mimeType="audio/mp4" lang="de" label="Deutsch">
+      <Role schemeIdUri="urn:mpeg:dash:role:2011" value="dub"/>
+      <AudioChannelConfiguration schemeIdUri="urn:mpeg:dash:23003:3:audio_channel_configuration:2011" value="2"/>
+      <Representation id="audio_de" bandwidth="128000" codecs="mp4a.40.2">
+        <BaseURL>audio_de.mp4</BaseURL>
+      </Representation>
+      <Representation id="audio_de_51" bandwidth="384000" codecs="ec-3">
+        <AudioChannelConfiguration schemeIdUri="tag:dolby.com,2014:dash:audio_channel_configuration:2011" value="F801"/>
+      </Representation>
+    </AdaptationSet>
+    <AdaptationSet contentType="text" mimeType="application/mp4" codecs="stpp" lang="en">
+      <Label>English CC</Label>
+      <Role schemeIdUri="urn:mpeg:dash:role:2011" value="caption"/>
+      <Representation id="sub_en" bandwidth="2000"/>
+    </AdaptationSet>
+    <AdaptationSet mimeType="image/jpeg">
+      <Representation id="thumbnails" bandwidth="10000"/>
+    </AdaptationSet>
+  </Period>
+</MPD>"#;
+
+    fn info() -> StreamInfo {
+        parse(MPD, &Url::parse("https://example.com/vod/manifest.mpd").unwrap()).unwrap()
+    }
+
+    #[test]
+    fn parses_video_representations() {
+        let info = info();
+        let video: Vec<_> = info.video_streams.iter()
+            .map(|v| (v.resolution.as_deref(), v.bandwidth, v.frame_rate, v.codecs.as_deref(), v.period, v.encrypted))
+            .collect();
+        assert_eq!(video, [
+            (Some("640x360"), Some(800000), Some(25.0), Some("avc1.4d401e"), Some(0), false),
+            (Some("1920x1080"), Some(5000000), Some(29.97), Some("avc1.640028"), Some(1), true),
+            (Some("1280x720"), Some(2500000), Some(60.0), Some("avc1.64001f"), Some(1), true),
+        ]);
+        assert_eq!(info.video_streams[0].url.as_deref(), Some("https://example.com/vod/media/ad.mp4"));
+        assert_eq!(info.video_streams[1].role.as_deref(), Some("main"));
+        assert_eq!(info.video_streams[1].group_id.as_deref(), Some("v1080"));
+    }
+
+    #[test]
+    fn parses_audio_representations() {
+        let info = info();
+        let audio: Vec<_> = info.audio_streams.iter()
+            .map(|a| (a.group_id.as_deref(), a.name.as_deref(), a.language.as_deref(), a.channels.as_deref(), a.role.as_deref()))
+            .collect();
+        assert_eq!(audio, [
+            (Some("audio_de"), Some("Deutsch"), Some("de"), Some("2"), Some("dub")),
+            (Some("audio_de_51"), Some("Deutsch"), Some("de"), Some("F801"), Some("dub")),
+        ]);
+        assert_eq!(info.audio_streams[0].url.as_deref(), Some("https://example.com/vod/media/main/audio_de.mp4"));
+        assert!(!info.audio_streams[0].encrypted);
+    }
+
+    #[test]
+    fn parses_subtitles_and_skips_thumbnails() {
+        let info = info();
+        assert_eq!(info.subtitle_streams.len(), 1);
+        let subtitle = &info.subtitle_streams[0];
+        assert_eq!(
+            (subtitle.group_id.as_deref(), subtitle.name.as_deref(), subtitle.language.as_deref()),
+            (Some("sub_en"), Some("English CC"), Some("en")),
+        );
+        assert_eq!(subtitle.role.as_deref(), Some("caption"));
+    }
+
+    #[test]
+    fn ids_are_stable_and_unique() {
+        let (first, second) = (info(), info());
+        assert_eq!(first.video_streams[1].id, second.video_streams[1].id);
+        let mut ids: Vec<&str> = first.video_streams.iter().map(|v| v.id.as_str())
+            .chain(first.audio_streams.iter().map(|a| a.id.as_str()))
+            .chain(first.subtitle_streams.iter().map(|s| s.id.as_str()))
+            .collect();
+        let count = ids.len();
+        ids.sort();
+        ids.dedup();
+        assert_eq!(ids.len(), count);
+    }
+
+    #[test]
+    fn rejects_other_documents() {
+        let base = Url::parse("https://example.com/").unwrap();
+        assert!(parse("<SmoothStreamingMedia/>", &base).is_err());
+        assert!(parse("<MPD", &base).is_err());
+    }
+
+    #[test]
+    fn frame_rates() {
+        assert_eq!(parse_frame_rate("24"), Some(24.0));
+        assert_eq!(parse_frame_rate("24000/1001"), Some(23.976));
+        assert_eq!(parse_frame_rate("1/0"), None);
+    }
+}
@@ -0,0 +1,196 @@
+use std::fmt;
+use std::sync::OnceLock;
+use regex::Regex;
+use serde::Serialize;
+
+use crate::progress::strip_ansi;
+use crate::streams::StreamInfo;
+
+// Lines of N_m3u8DL-RE output kept with an error
+const EXCERPT_LINES: usize = 20;
+
+#[derive(Debug, Clone, Copy, Serialize, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum DiscoveryErrorKind {
+    // The manifest could not be fetched or read
+    Network,
+    // The server answered with an error status
+    HttpStatus,
+    // The manifest was read but is not something streams can be listed from
+    Parse,
+    BinaryMissing,
+    Timeout,
+    Cancelled,
+    // N_m3u8DL-RE failed without saying why in a way we recognize
+    Other,
+}
+
+// Why no stream list could be loaded. Returned from `load_m3u8_options` and
+// emitted as `m3u8-options-error`.
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct DiscoveryError {
+    pub kind: DiscoveryErrorKind,
+    pub message: String,
+    // Only for `HttpStatus`
+    pub status: Option<u16>,
+    // The end of what N_m3u8DL-RE printed, when it was involved
+    pub output: Option<String>,
+}
+
+impl DiscoveryError {
+    pub fn new(kind: DiscoveryErrorKind, message: impl Into<String>) -> Self {
+        DiscoveryError { kind, message: message.into(), status: None, output: None }
+    }
+
+    pub fn http_status(status: u16, message: impl Into<String>) -> Self {
+        DiscoveryError { status: Some(status), ..Self::new(DiscoveryErrorKind::HttpStatus, message) }
+    }
+
+    pub fn with_output(mut self, output: &str) -> Self {
+        self.output = excerpt(output);
+        self
+    }
+
+    // Works out from N_m3u8DL-RE's output why a probe found no streams
+    pub fn from_probe_output(stdout: &str, stderr: &str) -> Self {
+        let output = strip_ansi(&format!("{}\n{}", stdout, stderr));
+        let status = status_pattern().captures(&output).and_then(|captures| captures[1].parse::<u16>().ok());
+        let lower = output.to_lowercase();
+        let error = if let Some(status) = status {
+            Self::http_status(status, format!("The server answered with HTTP {}", status))
+        } else if NETWORK_ERRORS.iter().any(|pattern| lower.contains(pattern)) {
+            Self::new(DiscoveryErrorKind::Network, "N_m3u8DL-RE could not reach the server")
+        } else if PARSE_ERRORS.iter().any(|pattern| lower.contains(pattern)) {
+            Self::new(DiscoveryErrorKind::Parse, "N_m3u8DL-RE could not read the manifest")
+        } else {
+            Self::new(DiscoveryErrorKind::Other, "N_m3u8DL-RE found no streams")
+        };
+        // stderr is usually empty, N_m3u8DL-RE logs its errors to stdout
+        let relevant = if stderr.trim().is_empty() { stdout } else { stderr };
+        error.with_output(relevant)
+    }
+}
+
+impl fmt::Display for DiscoveryError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.message)
+    }
+}
+
+// Commands that also do other work report errors as plain strings
+impl From<DiscoveryError> for String {
+    fn from(error: DiscoveryError) -> String {
+        error.message
+    }
+}
+
+const NETWORK_ERRORS: &[&str] = &[
+    "no such host",
+    "name or service not known",
+    "name resolution",
+    "connection refused",
+    "connection reset",
+    "network is unreachable",
+    "ssl connection could not be established",
+    "timed out",
+    "socketexception",
+];
+
+const PARSE_ERRORS: &[&str] = &["not supported", "unsupported", "failed to parse", "invalid", "xmlexception", "jsonexception"];
+
+// Matches .NET's "Response status code does not indicate success: 404 (Not Found)."
+fn status_pattern() -> &'static Regex {
+    static STATUS_PATTERN: OnceLock<Regex> = OnceLock::new();
+    STATUS_PATTERN.get_or_init(|| {
+        Regex::new(r"(?i)(?:status code does not indicate success|status code|http error)\D{0,3}([1-5]\d\d)\b").unwrap()
+    })
+}
+
+fn excerpt(output: &str) -> Option<String> {
+    let output = strip_ansi(output);
+    let lines: Vec<&str> = output.lines().map(str::trim_end).filter(|line| !line.trim().is_empty()).collect();
+    if lines.is_empty() {
+        return None;
+    }
+    Some(lines[lines.len().saturating_sub(EXCERPT_LINES)..].join("\n"))
+}
+
+// Streams N_m3u8DL-RE can decrypt by itself: HLS AES keys come with the playlist
+fn needs_license(encrypted: bool, encryption: Option<&str>) -> bool {
+    encrypted && !matches!(encryption, Some("AES_128" | "AES_128_ECB" | "CHACHA20"))
+}
+
+// Fails when there is nothing to download
+pub fn check_streams(info: &StreamInfo) -> Result<(), DiscoveryError> {
+    if info.video_streams.is_empty() && info.audio_streams.is_empty() && info.subtitle_streams.is_empty() {
+        return Err(DiscoveryError::new(DiscoveryErrorKind::Other, "The manifest lists no streams"));
+    }
+    Ok(())
+}
+
+// Whether every video and audio stream is behind DRM
+pub fn is_drm_only(info: &StreamInfo) -> bool {
+    let mut media = info.video_streams.iter().map(|video| needs_license(video.encrypted, video.encryption.as_deref()))
+        .chain(info.audio_streams.iter().map(|audio| needs_license(audio.encrypted, audio.encryption.as_deref())))
+        .peekable();
+    media.peek().is_some() && media.all(|drm| drm)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::streams::{AudioStream, VideoStream};
+
+    #[test]
+    fn reads_http_status_from_output() {
+        let stdout = "\x1b[32m12:00:00.000 INFO\x1b[0m : N_m3u8DL-RE (Beta version) 20230628\n\
+            12:00:00.100 INFO : Loading URL: https://example.com/missing.m3u8\n\
+            12:00:00.500 ERROR: Response status code does not indicate success: 404 (Not Found).\n";
+        let error = DiscoveryError::from_probe_output(stdout, "");
+        assert_eq!((error.kind, error.status), (DiscoveryErrorKind::HttpStatus, Some(404)));
+        let output = error.output.unwrap();
+        assert!(output.ends_with("404 (Not Found)."), "{}", output);
+        assert!(!output.contains('\x1b'));
+    }
+
+    #[test]
+    fn recognizes_network_and_parse_failures() {
+        let network = DiscoveryError::from_probe_output("", "System.Net.Http.HttpRequestException: No such host is known. (nowhere.invalid:443)");
+        assert_eq!(network.kind, DiscoveryErrorKind::Network);
+        assert_eq!(network.output.as_deref(), Some("System.Net.Http.HttpRequestException: No such host is known. (nowhere.invalid:443)"));
+
+        let parse = DiscoveryError::from_probe_output("ERROR: Input file is not supported!", "");
+        assert_eq!(parse.kind, DiscoveryErrorKind::Parse);
+
+        let other = DiscoveryError::from_probe_output("INFO : Done", "");
+        assert_eq!(other.kind, DiscoveryErrorKind::Other);
+    }
+
+    #[test]
+    fn keeps_the_end_of_long_output() {
+        let stdout: String = (0..50).map(|line| format!("line {}\n", line)).collect();
+        let output = DiscoveryError::new(DiscoveryErrorKind::Other, "failed").with_output(&stdout).output.unwrap();
+        assert_eq!(output.lines().count(), EXCERPT_LINES);
+        assert!(output.starts_with("line 30") && output.ends_with("line 49"));
+        assert_eq!(DiscoveryError::new(DiscoveryErrorKind::Other, "failed").with_output("  \n").output, None);
+    }
+
+    #[test]
+    fn checks_for_drm_only_manifests() {
+        let video = |encrypted, encryption: Option<&str>| VideoStream {
+            encrypted,
+            encryption: encryption.map(String::from),
+            ..VideoStream::default()
+        };
+        let info = |video_streams, audio_streams| StreamInfo { video_streams, audio_streams, ..StreamInfo::default() };
+
+        let drm_only = info(vec![video(true, None)], vec![AudioStream { encrypted: true, ..AudioStream::default() }]);
+        assert!(is_drm_only(&drm_only));
+        // Still listed, the keys may be known
+        assert!(check_streams(&drm_only).is_ok());
+        assert!(!is_drm_only(&info(vec![video(true, Some("AES_128"))], Vec::new())));
+        assert!(!is_drm_only(&info(vec![video(true, Some("CENC")), video(false, None)], Vec::new())));
+        assert!(!is_drm_only(&StreamInfo::default()));
+        assert_eq!(check_streams(&StreamInfo::default()).unwrap_err().kind, DiscoveryErrorKind::Other);
+    }
+}
@@ -0,0 +1,321 @@
+use std::cmp::Reverse;
+use url::Url;
+
+use super::resolve;
+use crate::streams::{non_empty, stable_id, AudioStream, StreamInfo, SubtitleStream, VideoStream};
+
+// One #EXT-X-STREAM-INF entry
+#[derive(Debug, Clone, PartialEq)]
+pub struct Variant {
+    pub uri: String,
+    pub bandwidth: u64,
+    pub average_bandwidth: Option<u64>,
+    pub codecs: Option<String>,
+    pub resolution: Option<String>,
+    pub frame_rate: Option<f64>,
+    pub audio_group: Option<String>,
+    pub subtitle_group: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum MediaType {
+    Audio,
+    Video,
+    Subtitles,
+    ClosedCaptions,
+}
+
+// One #EXT-X-MEDIA entry
+#[derive(Debug, Clone, PartialEq)]
+pub struct Media {
+    pub media_type: MediaType,
+    pub uri: Option<String>,
+    pub group_id: String,
+    pub language: Option<String>,
+    pub name: String,
+    pub channels: Option<String>,
+    pub default: bool,
+}
+
+#[derive(Debug, Default, PartialEq)]
+pub struct MasterPlaylist {
+    pub variants: Vec<Variant>,
+    pub media: Vec<Media>,
+}
+
+impl MasterPlaylist {
+    pub fn is_master(&self) -> bool {
+        !self.variants.is_empty() || !self.media.is_empty()
+    }
+
+    // Variants are listed by bandwidth, highest first, like N_m3u8DL-RE does
+    pub fn into_stream_info(self) -> StreamInfo {
+        let mut variants = self.variants;
+        variants.sort_by_key(|variant| Reverse(variant.bandwidth));
+
+        let video_streams = variants
+            .into_iter()
+            .map(|variant| VideoStream {
+                // The variant URI is the only thing that sets variants apart reliably
+                id: stable_id("video", &[&variant.uri]),
+                resolution: variant.resolution,
+                bandwidth: Some(variant.bandwidth),
+                frame_rate: variant.frame_rate,
+                codecs: variant.codecs,
+                url: Some(variant.uri),
+                audio_group: variant.audio_group,
+                subtitle_group: variant.subtitle_group,
+                ..VideoStream::default()
+            })
+            .collect();
+
+        let mut audio_streams = Vec::new();
+        let mut subtitle_streams = Vec::new();
+        for media in self.media {
+            let identity = [
+                media.group_id.as_str(),
+                media.name.as_str(),
+                media.language.as_deref().unwrap_or_default(),
+                media.uri.as_deref().unwrap_or_default(),
+            ];
+            // N_m3u8DL-RE uses the GROUP-ID as GroupId
+            match media.media_type {
+                MediaType::Audio => audio_streams.push(AudioStream {
+                    id: stable_id("audio", &identity),
+                    group_id: non_empty(&media.group_id),
+                    name: non_empty(&media.name),
+                    language: media.language,
+                    channels: media.channels,
+                    url: media.uri,
+                    ..AudioStream::default()
+                }),
+                MediaType::Subtitles => subtitle_streams.push(SubtitleStream {
+                    id: stable_id("subtitle", &identity),
+                    group_id: non_empty(&media.group_id),
+                    name: non_empty(&media.name),
+                    language: media.language,
+                    url: media.uri,
+                    ..SubtitleStream::default()
+                }),
+                // Alternative angles and in-band captions cannot be picked separately
+                MediaType::Video | MediaType::ClosedCaptions => {}
+            }
+        }
+
+        StreamInfo { video_streams, audio_streams, subtitle_streams, drm_only: false }
+    }
+}
+
+pub fn is_playlist(text: &str) -> bool {
+    text.trim_start_matches('\u{feff}').trim_start().starts_with("#EXTM3U")
+}
+
+// Collects the variants and renditions of a master playlist. URIs are
+// resolved against `base`. Entries without the required attributes are skipped.
+pub fn parse_master(text: &str, base: &Url) -> MasterPlaylist {
+    let mut playlist = MasterPlaylist::default();
+    let mut pending: Option<Vec<(String, String)>> = None;
+
+    for line in text.lines().map(str::trim).filter(|line| !line.is_empty()) {
+        if let Some(attributes) = line.strip_prefix("#EXT-X-STREAM-INF:") {
+            pending = Some(parse_attributes(attributes));
+        } else if let Some(attributes) = line.strip_prefix("#EXT-X-MEDIA:") {
+            match parse_media(&parse_attributes(attributes), base) {
+                Some(media) => playlist.media.push(media),
+                None => println!("Skipping incomplete #EXT-X-MEDIA: {}", attributes),
+            }
+        } else if !line.starts_with('#') {
+            // The URI line that belongs to the preceding #EXT-X-STREAM-INF
+            if let Some(attributes) = pending.take() {
+                match parse_variant(&attributes, resolve(base, line)) {
+                    Some(variant) => playlist.variants.push(variant),
+                    None => println!("Skipping #EXT-X-STREAM-INF without BANDWIDTH: {}", line),
+                }
+            }
+        }
+    }
+
+    playlist
+}
+
+fn parse_variant(attributes: &[(String, String)], uri: String) -> Option<Variant> {
+    Some(Variant {
+        uri,
+        bandwidth: attribute(attributes, "BANDWIDTH")?.parse().ok()?,
+        average_bandwidth: attribute(attributes, "AVERAGE-BANDWIDTH").and_then(|value| value.parse().ok()),
+        codecs: attribute(attributes, "CODECS").map(String::from),
+        resolution: attribute(attributes, "RESOLUTION").map(String::from),
+        frame_rate: attribute(attributes, "FRAME-RATE").and_then(|value| value.parse().ok()),
+        audio_group: attribute(attributes, "AUDIO").map(String::from),
+        subtitle_group: attribute(attributes, "SUBTITLES").map(String::from),
+    })
+}
+
+fn parse_media(attributes: &[(String, String)], base: &Url) -> Option<Media> {
+    let media_type = match attribute(attributes, "TYPE")? {
+        "AUDIO" => MediaType::Audio,
+        "VIDEO" => MediaType::Video,
+        "SUBTITLES" => MediaType::Subtitles,
+        "CLOSED-CAPTIONS" => MediaType::ClosedCaptions,
+        _ => return None,
+    };
+    Some(Media {
+        media_type,
+        uri: attribute(attributes, "URI").map(|uri| resolve(base, uri)),
+        group_id: attribute(attributes, "GROUP-ID")?.to_string(),
+        language: attribute(attributes, "LANGUAGE").map(String::from),
+        name: attribute(attributes, "NAME").unwrap_or_default().to_string(),
+        channels: attribute(attributes, "CHANNELS").map(String::from),
+        default: attribute(attributes, "DEFAULT") == Some("YES"),
+    })
+}
+
+fn attribute<'a>(attributes: &'a [(String, String)], name: &str) -> Option<&'a str> {
+    attributes
+        .iter()
+        .find(|(key, _)| key == name)
+        .map(|(_, value)| value.as_str())
+}
+
+// Splits an attribute list like `BANDWIDTH=1280000,CODECS="avc1.4d401f,mp4a.40.2"`.
+// Quoted values may contain commas; the quotes are removed.
+fn parse_attributes(list: &str) -> Vec<(String, String)> {
+    let mut attributes = Vec::new();
+    let mut rest = list.trim();
+    while !rest.is_empty() {
+        let Some((key, after_key)) = rest.split_once('=') else { break };
+        let (value, after_value) = match after_key.strip_prefix('"') {
+            Some(quoted) => match quoted.split_once('"') {
+                Some((value, after)) => (value, after),
+                None => (quoted, ""),
+            },
+            None => after_key.split_once(',').map_or((after_key, ""), |(value, after)| (value, after)),
+        };
+        attributes.push((key.trim().to_string(), value.trim().to_string()));
+        rest = after_value.trim_start_matches(',').trim_start();
+    }
+    attributes
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MASTER: &str = r#"#EXTM3U
+#EXT-X-VERSION:6
+#EXT-X-INDEPENDENT-SEGMENTS
+
+#EXT-X-MEDIA:TYPE=AUDIO,GROUP-ID="aud1",LANGUAGE="en",NAME="English",DEFAULT=YES,AUTOSELECT=YES,CHANNELS="2",URI="audio/en/prog.m3u8"
+#EXT-X-MEDIA:TYPE=AUDIO,GROUP-ID="aud2",LANGUAGE="de",NAME="Deutsch, 5.1",CHANNELS="6",URI="audio/de/prog.m3u8"
+#EXT-X-MEDIA:TYPE=SUBTITLES,GROUP-ID="sub1",LANGUAGE="fr",NAME="Français",URI="https://cdn.example.com/subs/fr.m3u8"
+#EXT-X-MEDIA:TYPE=CLOSED-CAPTIONS,GROUP-ID="cc1",NAME="CC",INSTREAM-ID="CC1"
+
+#EXT-X-STREAM-INF:BANDWIDTH=2177116,AVERAGE-BANDWIDTH=2000000,CODECS="avc1.640020,mp4a.40.2",RESOLUTION=960x540,FRAME-RATE=29.970,AUDIO="aud1",SUBTITLES="sub1"
+v5/prog.m3u8
+#EXT-X-I-FRAME-STREAM-INF:BANDWIDTH=186522,URI="v5/iframe.m3u8"
+#EXT-X-STREAM-INF:BANDWIDTH=7968000,CODECS="avc1.64002a,mp4a.40.2",RESOLUTION=1920x1080,FRAME-RATE=60.000,AUDIO="aud2"
+/hd/prog.m3u8
+#EXT-X-STREAM-INF:RESOLUTION=640x360
+broken.m3u8
+"#;
+
+    fn base() -> Url {
+        Url::parse("https://example.com/show/master.m3u8?token=abc").unwrap()
+    }
+
+    #[test]
+    fn parses_quoted_attributes() {
+        assert_eq!(parse_attributes(r#"BANDWIDTH=1,CODECS="a,b", NAME="x=y",DEFAULT=YES"#), [
+            ("BANDWIDTH".to_string(), "1".to_string()),
+            ("CODECS".to_string(), "a,b".to_string()),
+            ("NAME".to_string(), "x=y".to_string()),
+            ("DEFAULT".to_string(), "YES".to_string()),
+        ]);
+    }
+
+    #[test]
+    fn parses_variants() {
+        let playlist = parse_master(MASTER, &base());
+        assert_eq!(playlist.variants, [
+            Variant {
+                uri: "https://example.com/show/v5/prog.m3u8".to_string(),
+                bandwidth: 2177116,
+                average_bandwidth: Some(2000000),
+                codecs: Some("avc1.640020,mp4a.40.2".to_string()),
+                resolution: Some("960x540".to_string()),
+                frame_rate: Some(29.97),
+                audio_group: Some("aud1".to_string()),
+                subtitle_group: Some("sub1".to_string()),
+            },
+            Variant {
+                uri: "https://example.com/hd/prog.m3u8".to_string(),
+                bandwidth: 7968000,
+                average_bandwidth: None,
+                codecs: Some("avc1.64002a,mp4a.40.2".to_string()),
+                resolution: Some("1920x1080".to_string()),
+                frame_rate: Some(60.0),
+                audio_group: Some("aud2".to_string()),
+                subtitle_group: None,
+            },
+        ]);
+    }
+
+    #[test]
+    fn parses_media() {
+        let playlist = parse_master(MASTER, &base());
+        assert_eq!(playlist.media.len(), 4);
+        assert_eq!(playlist.media[1], Media {
+            media_type: MediaType::Audio,
+            uri: Some("https://example.com/show/audio/de/prog.m3u8".to_string()),
+            group_id: "aud2".to_string(),
+            language: Some("de".to_string()),
+            name: "Deutsch, 5.1".to_string(),
+            channels: Some("6".to_string()),
+            default: false,
+        });
+        assert!(playlist.media[0].default);
+        assert_eq!(playlist.media[2].uri.as_deref(), Some("https://cdn.example.com/subs/fr.m3u8"));
+        assert_eq!(playlist.media[3].media_type, MediaType::ClosedCaptions);
+    }
+
+    #[test]
+    fn fills_stream_info() {
+        let info = parse_master(MASTER, &base()).into_stream_info();
+        let hd = &info.video_streams[0];
+        assert_eq!(
+            (hd.resolution.as_deref(), hd.bandwidth, hd.frame_rate, hd.codecs.as_deref()),
+            (Some("1920x1080"), Some(7968000), Some(60.0), Some("avc1.64002a,mp4a.40.2")),
+        );
+        assert_eq!(hd.url.as_deref(), Some("https://example.com/hd/prog.m3u8"));
+        assert_eq!(info.video_streams[1].frame_rate, Some(29.97));
+        assert_ne!(info.video_streams[0].id, info.video_streams[1].id);
+
+        let audio: Vec<_> = info.audio_streams.iter()
+            .map(|a| (a.group_id.as_deref(), a.name.as_deref(), a.language.as_deref(), a.channels.as_deref()))
+            .collect();
+        assert_eq!(audio, [
+            (Some("aud1"), Some("English"), Some("en"), Some("2")),
+            (Some("aud2"), Some("Deutsch, 5.1"), Some("de"), Some("6")),
+        ]);
+
+        assert_eq!(info.subtitle_streams.len(), 1);
+        assert_eq!(info.subtitle_streams[0].name.as_deref(), Some("Français"));
+    }
+
+    #[test]
+    fn ids_survive_reordering() {
+        let info = parse_master(MASTER, &base()).into_stream_info();
+        let mut playlist = parse_master(MASTER, &base());
+        playlist.media.reverse();
+        let reversed = playlist.into_stream_info();
+        assert_eq!(info.audio_streams[0].id, reversed.audio_streams[1].id);
+    }
+
+    #[test]
+    fn media_playlist_is_not_master() {
+        let media = "#EXTM3U\n#EXT-X-TARGETDURATION:10\n#EXTINF:10,\nseg0.ts\n#EXT-X-ENDLIST\n";
+        assert!(is_playlist(media));
+        assert!(!parse_master(media, &base()).is_master());
+        assert!(!is_playlist("<?xml version=\"1.0\"?><MPD/>"));
+    }
+}
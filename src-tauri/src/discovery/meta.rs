@@ -0,0 +1,234 @@
+use serde_json::Value;
+
+use crate::streams::{non_empty, stable_id, AudioStream, StreamInfo, SubtitleStream, VideoStream};
+
+// Enum names in declaration order, for builds of N_m3u8DL-RE that write enums as numbers
+const MEDIA_TYPES: &[&str] = &["AUDIO", "VIDEO", "SUBTITLES", "CLOSED_CAPTIONS"];
+const ENCRYPT_METHODS: &[&str] = &["NONE", "AES_128", "AES_128_ECB", "SAMPLE_AES", "SAMPLE_AES_CTR", "CENC", "CHACHA20", "UNKNOWN"];
+const ROLES: &[&str] = &[
+    "Subtitle", "Main", "Alternate", "Supplementary", "Commentary", "Dub", "Description", "Sign", "Metadata", "ForcedSubtitle",
+];
+
+// What the segments of one stream's playlist add up to
+#[derive(Debug, Default, PartialEq)]
+struct PlaylistSummary {
+    segment_count: Option<usize>,
+    duration: Option<f64>,
+    encryption: Option<String>,
+}
+
+// Reads the list of StreamSpecs N_m3u8DL-RE writes to meta.json. Streams
+// without a media type are video, like N_m3u8DL-RE treats them.
+pub fn parse(text: &str) -> Result<StreamInfo, String> {
+    let value: Value = serde_json::from_str(text.trim_start_matches('\u{feff}'))
+        .map_err(|e| format!("Invalid meta JSON: {}", e))?;
+    let specs = value.as_array().ok_or("Expected a list of streams in the meta JSON")?;
+
+    let mut info = StreamInfo::default();
+    // DASH period IDs are free text, streams get the index of their period instead
+    let mut periods: Vec<String> = Vec::new();
+    for spec in specs {
+        let period = text_field(spec, "PeriodId").map(|period_id| {
+            periods.iter().position(|known| *known == period_id).unwrap_or_else(|| {
+                periods.push(period_id.clone());
+                periods.len() - 1
+            })
+        });
+        let group_id = text_field(spec, "GroupId");
+        let bandwidth = field(spec, "Bandwidth").and_then(Value::as_u64);
+        let url = text_field(spec, "Url");
+        let role = enum_field(spec, "Role", ROLES).map(|role| role.to_lowercase());
+        let summary = summarize(field(spec, "Playlist"));
+        let encrypted = summary.encryption.is_some();
+
+        // Same identities native discovery uses, so a stream keeps its ID
+        // whichever way the manifest was read
+        let bandwidth_text = bandwidth.map(|bandwidth| bandwidth.to_string()).unwrap_or_default();
+        let period_text = period.map(|period| period.to_string()).unwrap_or_default();
+        let group_text = group_id.clone().unwrap_or_default();
+        let url_text = url.clone().unwrap_or_default();
+        let name = text_field(spec, "Name");
+        let language = text_field(spec, "Language");
+        let media_identity = |kind| match period {
+            Some(_) => stable_id(kind, &[&period_text, &group_text, &bandwidth_text]),
+            None => stable_id(kind, &[
+                &group_text,
+                name.as_deref().unwrap_or_default(),
+                language.as_deref().unwrap_or_default(),
+                &url_text,
+            ]),
+        };
+
+        match enum_field(spec, "MediaType", MEDIA_TYPES).as_deref() {
+            None | Some("VIDEO") => info.video_streams.push(VideoStream {
+                id: match period {
+                    Some(_) => stable_id("video", &[&period_text, &group_text, &bandwidth_text]),
+                    None => stable_id("video", &[&url_text]),
+                },
+                group_id,
+                resolution: text_field(spec, "Resolution"),
+                bandwidth,
+                frame_rate: field(spec, "FrameRate").and_then(Value::as_f64),
+                codecs: text_field(spec, "Codecs"),
+                url,
+                period,
+                role,
+                encrypted,
+                segment_count: summary.segment_count,
+                duration: summary.duration,
+                encryption: summary.encryption,
+                ..VideoStream::default()
+            }),
+            Some("AUDIO") => info.audio_streams.push(AudioStream {
+                id: media_identity("audio"),
+                group_id,
+                name,
+                language,
+                channels: text_field(spec, "Channels"),
+                bandwidth,
+                codecs: text_field(spec, "Codecs"),
+                url,
+                period,
+                role,
+                encrypted,
+                segment_count: summary.segment_count,
+                duration: summary.duration,
+                encryption: summary.encryption,
+            }),
+            Some("SUBTITLES") => info.subtitle_streams.push(SubtitleStream {
+                id: media_identity("subtitle"),
+                group_id,
+                name,
+                language,
+                codecs: text_field(spec, "Codecs"),
+                url,
+                period,
+                role,
+                segment_count: summary.segment_count,
+                duration: summary.duration,
+            }),
+            Some(other) => println!("Skipping {} stream {:?} from the meta JSON", other, group_id),
+        }
+    }
+
+    Ok(info)
+}
+
+// Playlist is only filled in for streams whose playlist N_m3u8DL-RE has loaded
+fn summarize(playlist: Option<&Value>) -> PlaylistSummary {
+    let Some(playlist) = playlist.filter(|playlist| playlist.is_object()) else {
+        return PlaylistSummary::default();
+    };
+    let segments: Vec<&Value> = field(playlist, "MediaParts")
+        .and_then(Value::as_array)
+        .into_iter()
+        .flatten()
+        .filter_map(|part| field(part, "MediaSegments").and_then(Value::as_array))
+        .flatten()
+        .collect();
+
+    let init = field(playlist, "MediaInit").filter(|init| init.is_object());
+    let encryption = init.into_iter()
+        .chain(segments.iter().copied())
+        .filter_map(|segment| field(segment, "EncryptInfo"))
+        .filter_map(|info| enum_field(info, "Method", ENCRYPT_METHODS))
+        .find(|method| method != "NONE");
+
+    PlaylistSummary {
+        segment_count: Some(segments.len()),
+        duration: Some(segments.iter().filter_map(|segment| field(segment, "Duration").and_then(Value::as_f64)).sum()),
+        encryption,
+    }
+}
+
+// Property names are PascalCase, looked up without case in case that changes
+fn field<'a>(object: &'a Value, name: &str) -> Option<&'a Value> {
+    object.as_object()?
+        .iter()
+        .find(|(key, _)| key.eq_ignore_ascii_case(name))
+        .map(|(_, value)| value)
+        .filter(|value| !value.is_null())
+}
+
+fn text_field(object: &Value, name: &str) -> Option<String> {
+    field(object, name).and_then(Value::as_str).and_then(non_empty)
+}
+
+// Enums are written by name or by number depending on the build
+fn enum_field(object: &Value, name: &str, names: &[&str]) -> Option<String> {
+    match field(object, name)? {
+        Value::String(text) => non_empty(text),
+        Value::Number(number) => names.get(number.as_u64()? as usize).map(|name| name.to_string()),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DASH: &str = include_str!("fixtures/meta_dash.json");
+
+    #[test]
+    fn reads_dash_streams_across_periods() {
+        let info = parse(DASH).unwrap();
+        let video: Vec<_> = info.video_streams.iter()
+            .map(|v| (v.group_id.as_deref(), v.resolution.as_deref(), v.period, v.segment_count, v.duration))
+            .collect();
+        assert_eq!(video, [
+            (Some("ad-video"), Some("640x360"), Some(0), Some(2), Some(8.0)),
+            (Some("v1080"), Some("1920x1080"), Some(1), Some(3), Some(10.5)),
+        ]);
+        assert_eq!(info.video_streams[1].frame_rate, Some(29.97));
+        assert_eq!(info.video_streams[1].role.as_deref(), Some("main"));
+    }
+
+    #[test]
+    fn reads_encryption_from_segments_and_init() {
+        let info = parse(DASH).unwrap();
+        assert_eq!(info.video_streams[0].encryption, None);
+        assert!(!info.video_streams[0].encrypted);
+        // Only the init segment says the main period is encrypted
+        assert_eq!(info.video_streams[1].encryption.as_deref(), Some("CENC"));
+        assert!(info.video_streams[1].encrypted);
+        assert_eq!(info.audio_streams[0].encryption.as_deref(), Some("CENC"));
+    }
+
+    #[test]
+    fn reads_audio_and_subtitles() {
+        let info = parse(DASH).unwrap();
+        let audio = &info.audio_streams[0];
+        assert_eq!(
+            (audio.group_id.as_deref(), audio.language.as_deref(), audio.channels.as_deref(), audio.period),
+            (Some("audio_de"), Some("de"), Some("2"), Some(1)),
+        );
+        assert_eq!(info.subtitle_streams.len(), 1);
+        assert_eq!(info.subtitle_streams[0].name.as_deref(), Some("English CC"));
+        // Subtitles whose playlist was not loaded have no segment data
+        assert_eq!(info.subtitle_streams[0].segment_count, None);
+    }
+
+    #[test]
+    fn reads_numeric_enums() {
+        let info = parse(r#"[
+            { "GroupId": "aud", "MediaType": 0, "Language": "en", "Url": "https://example.com/en.m3u8",
+              "Playlist": { "MediaParts": [{ "MediaSegments": [
+                { "Duration": 6.0, "EncryptInfo": { "Method": 1 } },
+                { "Duration": 4.0, "EncryptInfo": { "Method": 1 } }
+              ] }] } },
+            { "Bandwidth": 2000000, "Resolution": "1280x720", "Url": "https://example.com/720.m3u8", "Role": 1 },
+            { "MediaType": 3, "GroupId": "cc" }
+        ]"#).unwrap();
+        assert_eq!(info.audio_streams[0].encryption.as_deref(), Some("AES_128"));
+        assert_eq!(info.audio_streams[0].duration, Some(10.0));
+        assert_eq!(info.video_streams[0].role.as_deref(), Some("main"));
+        assert_eq!(info.video_streams[0].id, stable_id("video", &["https://example.com/720.m3u8"]));
+        assert!(info.subtitle_streams.is_empty());
+    }
+
+    #[test]
+    fn rejects_other_documents() {
+        assert!(parse("{}").is_err());
+        assert!(parse("INFO : Vid 1920x1080").is_err());
+    }
+}
@@ -0,0 +1,161 @@
+use std::fs;
+use std::io::Read;
+use std::path::Path;
+use std::time::Duration;
+use roxmltree::Node;
+use serde::{Deserialize, Serialize};
+use url::Url;
+
+use crate::streams::StreamInfo;
+use crate::Header;
+
+pub use error::{check_streams, is_drm_only, DiscoveryError, DiscoveryErrorKind};
+
+mod dash;
+mod error;
+mod hls;
+pub mod meta;
+pub mod probes;
+mod smooth;
+
+// How `load_m3u8_options` lists the streams of a manifest
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, Default, PartialEq, Eq, Hash)]
+#[serde(rename_all = "snake_case")]
+pub enum DiscoveryMode {
+    // Parse the manifest here, and ask N_m3u8DL-RE only for what cannot be parsed
+    #[default]
+    Native,
+    // Always ask N_m3u8DL-RE for its meta JSON. Slower, but has segment counts,
+    // durations and encryption of every stream.
+    MetaJson,
+}
+
+// Manifest as read from the network or disk, with the URL relative URIs resolve against
+pub struct Manifest {
+    pub base: Url,
+    pub text: String,
+}
+
+// Reads the manifest behind `url` and lists its streams without running
+// N_m3u8DL-RE. Returns `None` for manifests that cannot be parsed natively,
+// so the caller can fall back to the binary.
+pub fn discover(url: &str, headers: &[Header], timeout: Duration) -> Result<Option<StreamInfo>, DiscoveryError> {
+    let manifest = load_manifest(url, headers, timeout)?;
+    if hls::is_playlist(&manifest.text) {
+        let playlist = hls::parse_master(&manifest.text, &manifest.base);
+        if playlist.is_master() {
+            return Ok(Some(playlist.into_stream_info()));
+        }
+        // A media playlist is a single stream, there is nothing to pick from
+        println!("{} is a media playlist", url);
+    } else if dash::is_manifest(&manifest.text) {
+        return dash::parse(&manifest.text, &manifest.base).map(Some).map_err(parse_error);
+    } else if smooth::is_manifest(&manifest.text) {
+        return smooth::parse(&manifest.text).map(Some).map_err(parse_error);
+    }
+    Ok(None)
+}
+
+fn parse_error(message: String) -> DiscoveryError {
+    DiscoveryError::new(DiscoveryErrorKind::Parse, message)
+}
+
+pub fn load_manifest(url: &str, headers: &[Header], timeout: Duration) -> Result<Manifest, DiscoveryError> {
+    match Url::parse(url) {
+        Ok(base) if matches!(base.scheme(), "http" | "https") => fetch(base, headers, timeout),
+        Ok(base) if base.scheme() == "file" => {
+            let path = base.to_file_path()
+                .map_err(|_| DiscoveryError::new(DiscoveryErrorKind::Parse, format!("Invalid file URL: {}", url)))?;
+            read_local(&path)
+        }
+        // Anything else, including Windows paths like C:\..., is a local path
+        _ => read_local(Path::new(url)),
+    }
+}
+
+// Relative URIs resolve against the URL the manifest was served from, which
+// differs from `url` after a redirect
+fn fetch(url: Url, headers: &[Header], timeout: Duration) -> Result<Manifest, DiscoveryError> {
+    println!("Fetching manifest: {}", url);
+    let mut request = ureq::get(url.as_str()).timeout(timeout);
+    for header in headers {
+        request = request.set(&header.key, &header.value);
+    }
+    let mut bytes = Vec::new();
+    let response = match request.call() {
+        Ok(response) => response,
+        Err(ureq::Error::Status(status, response)) => {
+            return Err(DiscoveryError::http_status(
+                status,
+                format!("The server answered {} {} for {}", status, response.status_text(), url),
+            ));
+        }
+        Err(e) => return Err(DiscoveryError::new(DiscoveryErrorKind::Network, format!("Failed to fetch {}: {}", url, e))),
+    };
+    let base = match Url::parse(response.get_url()) {
+        Ok(final_url) if final_url != url => {
+            println!("Manifest redirected to: {}", final_url);
+            final_url
+        }
+        _ => url,
+    };
+    response.into_reader()
+        .read_to_end(&mut bytes)
+        .map_err(|e| DiscoveryError::new(DiscoveryErrorKind::Network, format!("Failed to read {}: {}", base, e)))?;
+    Ok(Manifest { base, text: decode_text(&bytes) })
+}
+
+fn read_local(path: &Path) -> Result<Manifest, DiscoveryError> {
+    println!("Reading manifest from: {:?}", path);
+    let unreadable = |message: String| DiscoveryError::new(DiscoveryErrorKind::Network, message);
+    let path = fs::canonicalize(path).map_err(|e| unreadable(format!("Failed to open {:?}: {}", path, e)))?;
+    let text = fs::read(&path)
+        .map(|bytes| decode_text(&bytes))
+        .map_err(|e| unreadable(format!("Failed to read {:?}: {}", path, e)))?;
+    let base = Url::from_file_path(&path)
+        .map_err(|_| DiscoveryError::new(DiscoveryErrorKind::Parse, format!("Invalid manifest path: {:?}", path)))?;
+    Ok(Manifest { base, text })
+}
+
+// Smooth Streaming manifests are often UTF-16, everything else is UTF-8
+fn decode_text(bytes: &[u8]) -> String {
+    let utf16 = |bytes: &[u8], from_bytes: fn([u8; 2]) -> u16| {
+        let units: Vec<u16> = bytes.chunks_exact(2).map(|pair| from_bytes([pair[0], pair[1]])).collect();
+        String::from_utf16_lossy(&units)
+    };
+    match bytes {
+        [0xff, 0xfe, rest @ ..] => utf16(rest, u16::from_le_bytes),
+        [0xfe, 0xff, rest @ ..] => utf16(rest, u16::from_be_bytes),
+        [0xef, 0xbb, 0xbf, rest @ ..] => String::from_utf8_lossy(rest).into_owned(),
+        _ => String::from_utf8_lossy(bytes).into_owned(),
+    }
+}
+
+// Resolves a URI from a manifest against the manifest's own location
+pub fn resolve(base: &Url, uri: &str) -> String {
+    base.join(uri).map(String::from).unwrap_or_else(|_| uri.to_string())
+}
+
+// Child elements of an XML node with the given tag name, for the DASH and
+// Smooth Streaming parsers
+fn children<'a, 'input>(node: Node<'a, 'input>, name: &'static str) -> impl Iterator<Item = Node<'a, 'input>> {
+    node.children().filter(move |child| child.is_element() && child.tag_name().name() == name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn decodes_byte_order_marks() {
+        let utf16le: Vec<u8> = [0xff, 0xfe].into_iter()
+            .chain("<SmoothStreamingMedia/>".encode_utf16().flat_map(u16::to_le_bytes))
+            .collect();
+        assert_eq!(decode_text(&utf16le), "<SmoothStreamingMedia/>");
+        let utf16be: Vec<u8> = [0xfe, 0xff].into_iter()
+            .chain("<MPD/>".encode_utf16().flat_map(u16::to_be_bytes))
+            .collect();
+        assert_eq!(decode_text(&utf16be), "<MPD/>");
+        assert_eq!(decode_text(b"\xef\xbb\xbf#EXTM3U"), "#EXTM3U");
+    }
+}
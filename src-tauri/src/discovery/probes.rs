@@ -0,0 +1,145 @@
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use super::DiscoveryMode;
+use crate::streams::StreamInfo;
+use crate::Header;
+
+// How long a discovered stream list is reused before the manifest is read again
+pub const CACHE_TTL: Duration = Duration::from_secs(5 * 60);
+
+// Set by `cancel_probe`, checked by the probe while it waits
+#[derive(Debug, Clone, Default)]
+pub struct CancelToken(Arc<AtomicBool>);
+
+impl CancelToken {
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::SeqCst);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::SeqCst)
+    }
+}
+
+// Headers are part of the key since they can change what the server returns.
+// Their order does not matter.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+struct CacheKey {
+    url: String,
+    headers: Vec<(String, String)>,
+    mode: DiscoveryMode,
+}
+
+impl CacheKey {
+    fn new(url: &str, headers: &[Header], mode: DiscoveryMode) -> Self {
+        let mut headers: Vec<(String, String)> = headers.iter()
+            .map(|header| (header.key.to_lowercase(), header.value.clone()))
+            .collect();
+        headers.sort();
+        CacheKey { url: url.to_string(), headers, mode }
+    }
+}
+
+// Stream lists discovered recently and the probes still running
+#[derive(Debug, Default)]
+pub struct Probes {
+    cache: HashMap<CacheKey, (Instant, StreamInfo)>,
+    running: HashMap<u64, CancelToken>,
+    next_id: u64,
+}
+
+impl Probes {
+    pub fn cached(&mut self, url: &str, headers: &[Header], mode: DiscoveryMode, now: Instant) -> Option<StreamInfo> {
+        self.cache.retain(|_, (stored_at, _)| now.duration_since(*stored_at) < CACHE_TTL);
+        self.cache.get(&CacheKey::new(url, headers, mode)).map(|(_, info)| info.clone())
+    }
+
+    pub fn store(&mut self, url: &str, headers: &[Header], mode: DiscoveryMode, info: StreamInfo, now: Instant) {
+        self.cache.insert(CacheKey::new(url, headers, mode), (now, info));
+    }
+
+    pub fn start(&mut self) -> (u64, CancelToken) {
+        self.next_id += 1;
+        let token = CancelToken::default();
+        self.running.insert(self.next_id, token.clone());
+        (self.next_id, token)
+    }
+
+    pub fn finish(&mut self, probe_id: u64) {
+        self.running.remove(&probe_id);
+    }
+
+    // Cancels one probe, or every running probe without an ID
+    pub fn cancel(&mut self, probe_id: Option<u64>) -> Result<(), String> {
+        match probe_id {
+            Some(probe_id) => self.running.get(&probe_id)
+                .ok_or_else(|| format!("No stream probe {} is running", probe_id))?
+                .cancel(),
+            None => self.running.values().for_each(CancelToken::cancel),
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn header(key: &str, value: &str) -> Header {
+        Header { key: key.to_string(), value: value.to_string() }
+    }
+
+    fn info(resolution: &str) -> StreamInfo {
+        let mut info = StreamInfo::default();
+        info.video_streams.push(crate::streams::VideoStream {
+            resolution: Some(resolution.to_string()),
+            ..Default::default()
+        });
+        info
+    }
+
+    #[test]
+    fn reuses_results_until_they_expire() {
+        let mut probes = Probes::default();
+        let start = Instant::now();
+        let url = "https://example.com/master.m3u8";
+        probes.store(url, &[], DiscoveryMode::Native, info("1920x1080"), start);
+
+        assert_eq!(probes.cached(url, &[], DiscoveryMode::Native, start + Duration::from_secs(60)), Some(info("1920x1080")));
+        assert_eq!(probes.cached(url, &[], DiscoveryMode::Native, start + CACHE_TTL), None);
+        assert!(probes.cache.is_empty());
+    }
+
+    #[test]
+    fn keys_on_headers_and_mode() {
+        let mut probes = Probes::default();
+        let now = Instant::now();
+        let url = "https://example.com/manifest.mpd";
+        let headers = [header("Cookie", "a=1"), header("Referer", "https://example.com")];
+        probes.store(url, &headers, DiscoveryMode::Native, info("1280x720"), now);
+
+        let reordered = [header("referer", "https://example.com"), header("cookie", "a=1")];
+        assert!(probes.cached(url, &reordered, DiscoveryMode::Native, now).is_some());
+        assert!(probes.cached(url, &[header("Cookie", "a=2")], DiscoveryMode::Native, now).is_none());
+        assert!(probes.cached(url, &headers, DiscoveryMode::MetaJson, now).is_none());
+    }
+
+    #[test]
+    fn cancels_running_probes() {
+        let mut probes = Probes::default();
+        let (first, first_token) = probes.start();
+        let (second, second_token) = probes.start();
+        assert_ne!(first, second);
+
+        probes.cancel(Some(first)).unwrap();
+        assert!(first_token.is_cancelled() && !second_token.is_cancelled());
+
+        probes.finish(first);
+        assert!(probes.cancel(Some(first)).is_err());
+        probes.cancel(None).unwrap();
+        assert!(second_token.is_cancelled());
+    }
+}
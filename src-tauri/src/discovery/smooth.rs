@@ -0,0 +1,173 @@
+use roxmltree::Document;
+
+use super::children;
+use crate::streams::{non_empty, stable_id, AudioStream, StreamInfo, SubtitleStream, VideoStream};
+
+pub fn is_manifest(text: &str) -> bool {
+    text.contains("<SmoothStreamingMedia")
+}
+
+// Lists the QualityLevels of every StreamIndex. Fragment URLs are templates,
+// so no stream gets a URL.
+pub fn parse(text: &str) -> Result<StreamInfo, String> {
+    let document = Document::parse(text).map_err(|e| format!("Invalid Smooth Streaming manifest: {}", e))?;
+    let media = document.root_element();
+    if media.tag_name().name() != "SmoothStreamingMedia" {
+        return Err(format!("Expected a SmoothStreamingMedia element, found {}", media.tag_name().name()));
+    }
+    // PlayReady protection applies to the whole presentation
+    let encrypted = children(media, "Protection").next().is_some();
+
+    let mut info = StreamInfo::default();
+    for (index, stream) in children(media, "StreamIndex").enumerate() {
+        let stream_type = stream.attribute("Type").unwrap_or_default();
+        // Name is optional, N_m3u8DL-RE falls back to the type in that case
+        let name = stream.attribute("Name").unwrap_or(stream_type).to_string();
+        let language = stream.attribute("Language").and_then(non_empty);
+
+        for level in children(stream, "QualityLevel") {
+            let attribute = |key| level.attribute(key).or_else(|| stream.attribute(key));
+            let bitrate = level.attribute("Bitrate").unwrap_or_default();
+            let bandwidth = bitrate.parse::<u64>().ok();
+            let codecs = codec(level.attribute("FourCC").unwrap_or_default(), level.attribute("CodecPrivateData"));
+            let identity = [stream_type, name.as_str(), bitrate];
+
+            match stream_type {
+                "video" => {
+                    let resolution = match (attribute("MaxWidth"), attribute("MaxHeight")) {
+                        (Some(width), Some(height)) => Some(format!("{}x{}", width, height)),
+                        _ => None,
+                    };
+                    info.video_streams.push(VideoStream {
+                        id: stable_id("video", &identity),
+                        group_id: Some(name.clone()),
+                        resolution,
+                        bandwidth,
+                        codecs: non_empty(&codecs),
+                        encrypted,
+                        ..VideoStream::default()
+                    });
+                }
+                "audio" => info.audio_streams.push(AudioStream {
+                    id: stable_id("audio", &identity),
+                    group_id: Some(name.clone()),
+                    name: Some(name.clone()),
+                    language: language.clone(),
+                    channels: level.attribute("Channels").and_then(non_empty),
+                    bandwidth,
+                    codecs: non_empty(&codecs),
+                    encrypted,
+                    ..AudioStream::default()
+                }),
+                "text" => info.subtitle_streams.push(SubtitleStream {
+                    id: stable_id("subtitle", &identity),
+                    group_id: Some(name.clone()),
+                    name: Some(name.clone()),
+                    language: language.clone(),
+                    codecs: non_empty(&codecs),
+                    ..SubtitleStream::default()
+                }),
+                _ => println!("Skipping StreamIndex {} of type {:?}", index, stream_type),
+            }
+        }
+    }
+
+    Ok(info)
+}
+
+// Turns a FourCC into the codec string DASH and HLS would use. For H.264 the
+// profile and level are read from the SPS in CodecPrivateData.
+fn codec(four_cc: &str, codec_private_data: Option<&str>) -> String {
+    match four_cc.to_uppercase().as_str() {
+        "H264" | "AVC1" | "DAVC" => match codec_private_data.and_then(avc_profile) {
+            Some(profile) => format!("avc1.{}", profile),
+            None => "avc1".to_string(),
+        },
+        "HVC1" | "HEV1" => four_cc.to_lowercase(),
+        "H265" | "HEVC" => "hvc1".to_string(),
+        "AACL" => "mp4a.40.2".to_string(),
+        "AACH" => "mp4a.40.5".to_string(),
+        "EC-3" => "ec-3".to_string(),
+        "AC-3" => "ac-3".to_string(),
+        "TTML" | "DFXP" => "stpp".to_string(),
+        _ => four_cc.to_lowercase(),
+    }
+}
+
+// Finds the SPS NAL unit (type 7) and returns its profile, constraint and level bytes as hex
+fn avc_profile(codec_private_data: &str) -> Option<String> {
+    let data = codec_private_data.to_lowercase();
+    data.match_indices("00000001")
+        .map(|(start, marker)| &data[start + marker.len()..])
+        .find(|nal| u8::from_str_radix(nal.get(..2).unwrap_or_default(), 16).is_ok_and(|header| header & 0x1f == 7))
+        .and_then(|sps| sps.get(2..8))
+        .map(String::from)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const VOD: &str = include_str!("fixtures/smooth_vod.xml");
+    const PROTECTED: &str = include_str!("fixtures/smooth_protected.xml");
+
+    #[test]
+    fn detects_manifest() {
+        assert!(is_manifest(VOD));
+        assert!(!is_manifest("#EXTM3U"));
+    }
+
+    #[test]
+    fn parses_video_quality_levels() {
+        let info = parse(VOD).unwrap();
+        let video: Vec<_> = info.video_streams.iter()
+            .map(|v| (v.resolution.as_deref(), v.bandwidth, v.codecs.as_deref()))
+            .collect();
+        assert_eq!(video, [
+            (Some("1920x1080"), Some(5000000), Some("avc1.64002a")),
+            (Some("1280x720"), Some(2962000), Some("avc1.42c01f")),
+            (Some("848x480"), Some(1427000), Some("avc1")),
+        ]);
+        assert!(info.video_streams.iter().all(|v| !v.encrypted && v.url.is_none()));
+        assert_ne!(info.video_streams[0].id, info.video_streams[1].id);
+    }
+
+    #[test]
+    fn parses_audio_stream_indexes() {
+        let info = parse(VOD).unwrap();
+        let audio: Vec<_> = info.audio_streams.iter()
+            .map(|a| (a.group_id.as_deref(), a.language.as_deref(), a.channels.as_deref(), a.codecs.as_deref()))
+            .collect();
+        assert_eq!(audio, [
+            (Some("audio_eng"), Some("eng"), Some("2"), Some("mp4a.40.2")),
+            (Some("audio_deu"), Some("deu"), Some("6"), Some("ec-3")),
+        ]);
+    }
+
+    #[test]
+    fn parses_text_stream_indexes() {
+        let info = parse(VOD).unwrap();
+        assert_eq!(info.subtitle_streams.len(), 1);
+        let subtitle = &info.subtitle_streams[0];
+        assert_eq!((subtitle.group_id.as_deref(), subtitle.language.as_deref()), (Some("textstream_eng"), Some("eng")));
+        assert_eq!(subtitle.codecs.as_deref(), Some("stpp"));
+    }
+
+    #[test]
+    fn marks_protected_streams() {
+        let info = parse(PROTECTED).unwrap();
+        assert_eq!(info.video_streams.len(), 1);
+        assert_eq!(info.video_streams[0].codecs.as_deref(), Some("hvc1"));
+        assert!(info.video_streams[0].encrypted);
+        // Unnamed stream indexes are identified by their type
+        assert_eq!(info.audio_streams[0].group_id.as_deref(), Some("audio"));
+        assert_eq!(info.audio_streams[0].channels.as_deref(), Some("2"));
+        assert!(info.audio_streams[0].encrypted);
+    }
+
+    #[test]
+    fn rejects_other_documents() {
+        assert!(parse("<MPD/>").is_err());
+        assert!(parse("not xml").is_err());
+    }
+}
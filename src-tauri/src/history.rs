@@ -0,0 +1,438 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use serde::{Deserialize, Serialize};
+
+use crate::jobs::{now_millis, Job, JobStatus};
+use crate::streams::{self, AudioStream, SubtitleStream, VideoStream};
+use crate::{DownloadOptions, Header};
+
+// Headers that carry credentials are never written to disk
+const SENSITIVE_HEADER_PARTS: [&str; 6] = ["authorization", "cookie", "token", "key", "secret", "session"];
+const REDACTED: &str = "<redacted>";
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct HistoryEntry {
+    pub id: u64,
+    pub url: String,
+    pub save_name: String,
+    pub headers: Vec<Header>,
+    #[serde(default, deserialize_with = "streams::deserialize_stored")]
+    pub video_quality: Option<VideoStream>,
+    #[serde(default, alias = "audio_stream", deserialize_with = "streams::deserialize_stored_list")]
+    pub audio_streams: Vec<AudioStream>,
+    #[serde(default, deserialize_with = "streams::deserialize_stored_list")]
+    pub subtitles: Vec<SubtitleStream>,
+    pub auto_merge: bool,
+    pub audio_only: bool,
+    #[serde(default)]
+    pub profile: Option<String>,
+    pub save_dir: String,
+    pub status: JobStatus,
+    pub exit_code: Option<i32>,
+    pub error: Option<String>,
+    pub output_path: Option<String>,
+    pub started_at: u64,
+    pub finished_at: Option<u64>,
+}
+
+impl HistoryEntry {
+    // Rebuilds the options of the original download, see `merge_headers` for
+    // how `headers` apply
+    pub fn to_download_options(&self, save_name: Option<String>, headers: Vec<Header>) -> Result<DownloadOptions, String> {
+        Ok(DownloadOptions {
+            url: self.url.clone(),
+            save_name: save_name.unwrap_or_else(|| self.save_name.clone()),
+            headers: merge_headers(&self.headers, headers)?,
+            video_quality: self.video_quality.clone(),
+            audio_streams: self.audio_streams.clone(),
+            subtitles: self.subtitles.clone(),
+            auto_merge: self.auto_merge,
+            audio_only: self.audio_only,
+            priority: 0,
+            profile: self.profile.clone(),
+        })
+    }
+}
+
+#[derive(Debug, Deserialize, Clone, Default)]
+pub struct HistoryQuery {
+    pub page: Option<usize>,
+    pub page_size: Option<usize>,
+    pub status: Option<JobStatus>,
+    // Case-insensitive match against URL and save name
+    pub search: Option<String>,
+    pub from: Option<u64>,
+    pub to: Option<u64>,
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct HistoryPage {
+    pub entries: Vec<HistoryEntry>,
+    pub total: usize,
+    pub page: usize,
+    pub page_size: usize,
+}
+
+// Layout of the history file. `next_id` is kept apart from the entries so an
+// ID is never handed out twice, even after entries were deleted; a running
+// job could otherwise finish an unrelated entry.
+#[derive(Debug, Deserialize, Default)]
+struct HistoryFile {
+    next_id: u64,
+    entries: Vec<HistoryEntry>,
+}
+
+// Older history files only held the list of entries
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum StoredHistory {
+    Current(HistoryFile),
+    Entries(Vec<HistoryEntry>),
+}
+
+#[derive(Debug, Default)]
+pub struct History {
+    path: PathBuf,
+    next_id: u64,
+    entries: Vec<HistoryEntry>,
+}
+
+impl History {
+    pub fn load(path: PathBuf) -> History {
+        println!("Loading history from: {:?}", path);
+        let file = fs::read_to_string(&path)
+            .ok()
+            .and_then(|contents| match serde_json::from_str(&contents) {
+                Ok(StoredHistory::Current(file)) => Some(file),
+                Ok(StoredHistory::Entries(entries)) => Some(HistoryFile { next_id: 0, entries }),
+                Err(e) => {
+                    println!("Failed to parse history file: {:?}", e);
+                    None
+                }
+            })
+            .unwrap_or_default();
+        History {
+            path,
+            next_id: file.entries.iter().map(|entry| entry.id).fold(file.next_id, u64::max),
+            entries: file.entries,
+        }
+    }
+
+    fn save(&self) -> Result<(), String> {
+        if let Some(parent) = self.path.parent() {
+            fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+        }
+        let file = serde_json::json!({ "next_id": self.next_id, "entries": self.entries });
+        let contents = serde_json::to_string_pretty(&file).map_err(|e| e.to_string())?;
+        fs::write(&self.path, contents).map_err(|e| {
+            println!("Failed to write history file: {:?}", e);
+            e.to_string()
+        })
+    }
+
+    pub fn record_start(&mut self, job: &Job) -> Result<u64, String> {
+        self.next_id += 1;
+        let id = self.next_id;
+        let options = &job.options;
+        self.entries.push(HistoryEntry {
+            id,
+            url: options.url.clone(),
+            save_name: options.save_name.clone(),
+            headers: redact_headers(&options.headers),
+            video_quality: options.video_quality.clone(),
+            audio_streams: options.audio_streams.clone(),
+            subtitles: options.subtitles.clone(),
+            auto_merge: options.auto_merge,
+            audio_only: options.audio_only,
+            profile: options.profile.clone(),
+            save_dir: job.save_dir.clone(),
+            status: job.status.clone(),
+            exit_code: None,
+            error: None,
+            output_path: None,
+            started_at: now_millis(),
+            finished_at: None,
+        });
+        self.save()?;
+        Ok(id)
+    }
+
+    pub fn record_finish(&mut self, id: u64, job: &Job) -> Result<(), String> {
+        let entry = self.entries
+            .iter_mut()
+            .find(|entry| entry.id == id)
+            .ok_or_else(|| format!("Unknown history entry: {}", id))?;
+        entry.status = job.status.clone();
+        entry.exit_code = job.exit_code;
+        entry.error = job.error.clone();
+        entry.finished_at = job.finished_at.or_else(|| Some(now_millis()));
+        if job.status == JobStatus::Completed {
+            entry.output_path = find_output_file(Path::new(&job.save_dir), &job.options.save_name)
+                .map(|path| path.to_string_lossy().into_owned());
+        }
+        self.save()
+    }
+
+    pub fn get(&self, id: u64) -> Option<&HistoryEntry> {
+        self.entries.iter().find(|entry| entry.id == id)
+    }
+
+    pub fn query(&self, query: &HistoryQuery) -> HistoryPage {
+        let search = query.search.as_ref().map(|s| s.to_lowercase());
+        let mut matching: Vec<&HistoryEntry> = self.entries
+            .iter()
+            .filter(|entry| query.status.as_ref().map_or(true, |status| &entry.status == status))
+            .filter(|entry| query.from.map_or(true, |from| entry.started_at >= from))
+            .filter(|entry| query.to.map_or(true, |to| entry.started_at <= to))
+            .filter(|entry| search.as_ref().map_or(true, |search| {
+                entry.url.to_lowercase().contains(search) || entry.save_name.to_lowercase().contains(search)
+            }))
+            .collect();
+        // Newest first
+        matching.sort_by(|a, b| b.started_at.cmp(&a.started_at).then(b.id.cmp(&a.id)));
+
+        let page = query.page.unwrap_or(1).max(1);
+        let page_size = query.page_size.unwrap_or(20).max(1);
+        HistoryPage {
+            total: matching.len(),
+            entries: matching
+                .into_iter()
+                .skip((page - 1) * page_size)
+                .take(page_size)
+                .cloned()
+                .collect(),
+            page,
+            page_size,
+        }
+    }
+
+    pub fn delete(&mut self, id: u64) -> Result<(), String> {
+        let len = self.entries.len();
+        self.entries.retain(|entry| entry.id != id);
+        if self.entries.len() == len {
+            return Err(format!("Unknown history entry: {}", id));
+        }
+        self.save()
+    }
+
+    pub fn clear(&mut self) -> Result<(), String> {
+        self.entries.clear();
+        self.save()
+    }
+}
+
+fn is_sensitive(key: &str) -> bool {
+    let key = key.to_lowercase();
+    SENSITIVE_HEADER_PARTS.iter().any(|part| key.contains(part))
+}
+
+pub fn redact_headers(headers: &[Header]) -> Vec<Header> {
+    headers
+        .iter()
+        .map(|header| Header {
+            key: header.key.clone(),
+            value: if is_sensitive(&header.key) { REDACTED.to_string() } else { header.value.clone() },
+        })
+        .collect()
+}
+
+// Same for the `--header "Key: value"` pairs of an N_m3u8DL-RE command line
+pub fn redact_args(args: &[String]) -> Vec<String> {
+    let mut redacted = args.to_vec();
+    for index in 1..redacted.len() {
+        if redacted[index - 1] != "--header" {
+            continue;
+        }
+        if let Some((key, _)) = redacted[index].split_once(':') {
+            if is_sensitive(key.trim()) {
+                redacted[index] = format!("{}: {}", key, REDACTED);
+            }
+        }
+    }
+    redacted
+}
+
+// Headers in `given` replace recorded ones of the same name, an empty value
+// drops the header. Fails while a redacted header has not been given again,
+// since the download would most likely be refused without it.
+pub fn merge_headers(recorded: &[Header], given: Vec<Header>) -> Result<Vec<Header>, String> {
+    let mut merged: Vec<Header> = recorded
+        .iter()
+        .filter(|recorded| !given.iter().any(|header| header.key.eq_ignore_ascii_case(&recorded.key)))
+        .cloned()
+        .collect();
+    merged.extend(given.into_iter().filter(|header| !header.value.is_empty()));
+
+    let missing: Vec<&str> = merged.iter()
+        .filter(|header| header.value == REDACTED)
+        .map(|header| header.key.as_str())
+        .collect();
+    if !missing.is_empty() {
+        return Err(format!(
+            "The values of these headers were not saved and have to be entered again: {}",
+            missing.join(", "),
+        ));
+    }
+    Ok(merged)
+}
+
+// N_m3u8DL-RE picks the extension itself, so look for the newest file named after the download
+fn find_output_file(save_dir: &Path, save_name: &str) -> Option<PathBuf> {
+    fs::read_dir(save_dir)
+        .ok()?
+        .filter_map(Result::ok)
+        .map(|entry| entry.path())
+        .filter(|path| path.is_file() && path.file_stem().is_some_and(|stem| stem == save_name))
+        .max_by_key(|path| fs::metadata(path).and_then(|m| m.modified()).ok())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::progress::DownloadProgress;
+
+    fn header(key: &str, value: &str) -> Header {
+        Header { key: key.to_string(), value: value.to_string() }
+    }
+
+    fn job(headers: Vec<Header>) -> Job {
+        let options: DownloadOptions = serde_json::from_value(serde_json::json!({
+            "url": "https://example.com/master.m3u8",
+            "save_name": "video",
+            "headers": serde_json::to_value(headers).unwrap(),
+            "auto_merge": true,
+            "audio_only": false,
+        })).unwrap();
+        Job {
+            id: 1,
+            options,
+            args: Vec::new(),
+            save_dir: "/nonexistent/downloads".to_string(),
+            tmp_dir: "/nonexistent/tmp".to_string(),
+            history_id: None,
+            status: JobStatus::Running,
+            progress: DownloadProgress::default(),
+            phases: Vec::new(),
+            exit_code: None,
+            error: None,
+            created_at: 0,
+            finished_at: None,
+        }
+    }
+
+    fn history_path(name: &str) -> PathBuf {
+        std::env::temp_dir().join(format!("m3u8-history-test-{}-{}-{}.json", name, std::process::id(), now_millis()))
+    }
+
+    #[test]
+    fn redacts_credentials() {
+        let headers = redact_headers(&[
+            header("Cookie", "session=abc"),
+            header("Authorization", "Bearer xyz"),
+            header("X-Api-Key", "123"),
+            header("Referer", "https://example.com"),
+        ]);
+        let values: Vec<&str> = headers.iter().map(|header| header.value.as_str()).collect();
+        assert_eq!(values, [REDACTED, REDACTED, REDACTED, "https://example.com"]);
+    }
+
+    #[test]
+    fn redacts_credentials_in_args() {
+        let args: Vec<String> = ["in.m3u8", "--header", "Cookie: a=1", "--header", "Referer: https://example.com", "--save-name", "Cookie: x"]
+            .iter()
+            .map(|arg| arg.to_string())
+            .collect();
+        assert_eq!(redact_args(&args), [
+            "in.m3u8", "--header", "Cookie: <redacted>", "--header", "Referer: https://example.com", "--save-name", "Cookie: x",
+        ]);
+    }
+
+    #[test]
+    fn records_start_and_finish() {
+        let path = history_path("record");
+        let mut history = History::load(path.clone());
+        let mut job = job(vec![header("Cookie", "session=abc")]);
+        let id = history.record_start(&job).unwrap();
+
+        job.status = JobStatus::Failed;
+        job.exit_code = Some(1);
+        job.error = Some("Process exited".to_string());
+        job.finished_at = Some(42);
+        history.record_finish(id, &job).unwrap();
+
+        let mut history = History::load(path.clone());
+        let entry = history.get(id).unwrap();
+        assert_eq!((&entry.status, entry.exit_code, entry.finished_at), (&JobStatus::Failed, Some(1), Some(42)));
+        assert_eq!(entry.headers[0].value, REDACTED);
+        assert!(!fs::read_to_string(&path).unwrap().contains("session=abc"));
+        assert!(history.record_finish(id + 1, &job).is_err());
+        fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn asks_for_redacted_headers_again() {
+        let path = history_path("redownload");
+        let mut history = History::load(path.clone());
+        let id = history.record_start(&job(vec![header("Cookie", "a=1"), header("Authorization", "Bearer x"), header("Referer", "r")])).unwrap();
+        let entry = history.get(id).unwrap().clone();
+        fs::remove_file(path).unwrap();
+
+        let error = entry.to_download_options(None, Vec::new()).unwrap_err();
+        assert!(error.ends_with(": Cookie, Authorization"), "{}", error);
+        let error = entry.to_download_options(None, vec![header("cookie", "a=2")]).unwrap_err();
+        assert!(error.ends_with(": Authorization"), "{}", error);
+
+        let options = entry.to_download_options(None, vec![header("cookie", "a=2"), header("Authorization", "")]).unwrap();
+        let headers: Vec<(&str, &str)> = options.headers.iter().map(|header| (header.key.as_str(), header.value.as_str())).collect();
+        assert_eq!(headers, [("Referer", "r"), ("cookie", "a=2")]);
+    }
+
+    #[test]
+    fn does_not_reuse_ids_after_deleting() {
+        let path = history_path("ids");
+        let mut history = History::load(path.clone());
+        let first = history.record_start(&job(Vec::new())).unwrap();
+        let second = history.record_start(&job(Vec::new())).unwrap();
+        history.delete(second).unwrap();
+        assert_eq!(history.record_start(&job(Vec::new())).unwrap(), 3);
+
+        history.clear().unwrap();
+        let mut history = History::load(path.clone());
+        assert_eq!((first, second, history.record_start(&job(Vec::new())).unwrap()), (1, 2, 4));
+        fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn reads_the_old_file_layout() {
+        let path = history_path("legacy");
+        let mut history = History::load(path.clone());
+        history.record_start(&job(Vec::new())).unwrap();
+        let entries = serde_json::to_value(&history.entries).unwrap();
+        fs::write(&path, entries.to_string()).unwrap();
+
+        let mut history = History::load(path.clone());
+        assert_eq!(history.entries.len(), 1);
+        assert_eq!(history.record_start(&job(Vec::new())).unwrap(), 2);
+        fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn filters_and_pages_queries() {
+        let path = history_path("query");
+        let mut history = History::load(path.clone());
+        for (save_name, status) in [("Movie", JobStatus::Completed), ("Show", JobStatus::Failed), ("movie 2", JobStatus::Completed)] {
+            let id = history.record_start(&job(Vec::new())).unwrap();
+            let entry = history.entries.iter_mut().find(|entry| entry.id == id).unwrap();
+            entry.save_name = save_name.to_string();
+            entry.status = status;
+            entry.started_at = id * 10;
+        }
+        fs::remove_file(path).unwrap();
+        let ids = |query: HistoryQuery| history.query(&query).entries.iter().map(|entry| entry.id).collect::<Vec<u64>>();
+        assert_eq!(ids(HistoryQuery::default()), [3, 2, 1]);
+        assert_eq!(ids(HistoryQuery { search: Some("MOVIE".to_string()), ..HistoryQuery::default() }), [3, 1]);
+        assert_eq!(ids(HistoryQuery { status: Some(JobStatus::Failed), ..HistoryQuery::default() }), [2]);
+        assert_eq!(ids(HistoryQuery { from: Some(15), to: Some(25), ..HistoryQuery::default() }), [2]);
+        assert_eq!(ids(HistoryQuery { page: Some(2), page_size: Some(2), ..HistoryQuery::default() }), [1]);
+    }
+}
@@ -0,0 +1,518 @@
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::process::{Child, Command, ExitStatus};
+use std::time::{SystemTime, UNIX_EPOCH};
+use serde::{Deserialize, Serialize};
+
+use crate::history::{merge_headers, redact_args, redact_headers};
+use crate::{DownloadOptions, Header};
+use crate::progress::{DownloadPhase, DownloadProgress, PhaseChange};
+use crate::queue::{DownloadQueue, QueueEntry};
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum JobStatus {
+    Queued,
+    Running,
+    Paused,
+    Completed,
+    Failed,
+    Cancelled,
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct Job {
+    pub id: u64,
+    pub options: DownloadOptions,
+    pub args: Vec<String>,
+    pub save_dir: String,
+    pub tmp_dir: String,
+    pub history_id: Option<u64>,
+    pub status: JobStatus,
+    pub progress: DownloadProgress,
+    // Every phase the current run has gone through, oldest first
+    pub phases: Vec<PhaseChange>,
+    pub exit_code: Option<i32>,
+    pub error: Option<String>,
+    pub created_at: u64,
+    pub finished_at: Option<u64>,
+}
+
+impl Job {
+    pub fn segments_dir(&self) -> PathBuf {
+        segments_dir(&self.tmp_dir, &self.options.save_name)
+    }
+}
+
+impl PersistedJob {
+    pub fn segments_dir(&self) -> PathBuf {
+        segments_dir(&self.tmp_dir, &self.options.save_name)
+    }
+}
+
+// N_m3u8DL-RE keeps the segments of a download in `<tmp-dir>/<save-name>`
+fn segments_dir(tmp_dir: &str, save_name: &str) -> PathBuf {
+    Path::new(tmp_dir).join(save_name)
+}
+
+// Payload of every per-job event, so the frontend can tell downloads apart
+#[derive(Debug, Serialize, Clone)]
+pub struct JobEvent<T: Serialize + Clone> {
+    pub job_id: u64,
+    pub data: T,
+}
+
+// What is kept on disk about a job that has not finished yet, so it can be
+// restarted with the same args (and thus the same --tmp-dir) after a restart.
+// Headers that carry credentials are redacted like in the history and have
+// to be given again to `resume_interrupted`.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct PersistedJob {
+    pub id: u64,
+    pub options: DownloadOptions,
+    pub args: Vec<String>,
+    pub save_dir: String,
+    pub tmp_dir: String,
+    pub history_id: Option<u64>,
+    pub created_at: u64,
+}
+
+// Layout of the job state file. `next_id` outlives the jobs so IDs are never
+// reused across restarts; logs and temporary files are named after them.
+#[derive(Debug, Serialize, Deserialize, Default)]
+struct JobState {
+    next_id: u64,
+    jobs: Vec<PersistedJob>,
+}
+
+// Older state files only held the list of jobs
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum StoredJobState {
+    Current(JobState),
+    Jobs(Vec<PersistedJob>),
+}
+
+#[derive(Debug, Default)]
+pub struct JobManager {
+    next_id: u64,
+    jobs: BTreeMap<u64, Job>,
+    children: HashMap<u64, Child>,
+    queue: DownloadQueue,
+    state_path: Option<PathBuf>,
+    // Jobs that were still in flight when the app last exited
+    resumable: Vec<PersistedJob>,
+}
+
+impl JobManager {
+    pub fn load(state_path: PathBuf) -> JobManager {
+        println!("Loading interrupted jobs from: {:?}", state_path);
+        let state = fs::read_to_string(&state_path)
+            .ok()
+            .and_then(|contents| match serde_json::from_str(&contents) {
+                Ok(StoredJobState::Current(state)) => Some(state),
+                Ok(StoredJobState::Jobs(jobs)) => Some(JobState { next_id: 0, jobs }),
+                Err(e) => {
+                    println!("Failed to parse job state file: {:?}", e);
+                    None
+                }
+            })
+            .unwrap_or_default();
+        println!("Found {} interrupted jobs", state.jobs.len());
+
+        JobManager {
+            next_id: state.jobs.iter().map(|job| job.id).fold(state.next_id, u64::max),
+            resumable: state.jobs,
+            state_path: Some(state_path),
+            ..JobManager::default()
+        }
+    }
+
+    // Writes every unfinished job, including the not yet resumed ones from the last run
+    fn persist(&self) {
+        let Some(path) = &self.state_path else { return };
+        let in_flight: Vec<PersistedJob> = self.jobs
+            .values()
+            .filter(|job| matches!(job.status, JobStatus::Queued | JobStatus::Running | JobStatus::Paused))
+            .map(|job| PersistedJob {
+                id: job.id,
+                options: DownloadOptions { headers: redact_headers(&job.options.headers), ..job.options.clone() },
+                args: redact_args(&job.args),
+                save_dir: job.save_dir.clone(),
+                tmp_dir: job.tmp_dir.clone(),
+                history_id: job.history_id,
+                created_at: job.created_at,
+            })
+            .chain(self.resumable.iter().cloned())
+            .collect();
+        let state = JobState { next_id: self.next_id, jobs: in_flight };
+
+        let result = path.parent()
+            .map_or(Ok(()), fs::create_dir_all)
+            .and_then(|_| fs::write(path, serde_json::to_string_pretty(&state).unwrap_or_default()));
+        if let Err(e) = result {
+            println!("Failed to write job state file: {:?}", e);
+        }
+    }
+
+    pub fn create(&mut self, options: DownloadOptions, args: Vec<String>, save_dir: String, tmp_dir: String) -> u64 {
+        self.next_id += 1;
+        let id = self.next_id;
+        self.queue.push(id, options.priority);
+        self.jobs.insert(id, Job {
+            id,
+            options,
+            args,
+            save_dir,
+            tmp_dir,
+            history_id: None,
+            status: JobStatus::Queued,
+            progress: DownloadProgress::default(),
+            phases: Vec::new(),
+            exit_code: None,
+            error: None,
+            created_at: now_millis(),
+            finished_at: None,
+        });
+        self.persist();
+        id
+    }
+
+    pub fn resumable(&self) -> Vec<PersistedJob> {
+        self.resumable.clone()
+    }
+
+    // Queues an interrupted job again under its old ID, ahead of new downloads.
+    // `headers` fill in the redacted ones, see `history::merge_headers`.
+    pub fn resume_interrupted(&mut self, id: u64, headers: Vec<Header>) -> Result<(), String> {
+        let position = self.resumable
+            .iter()
+            .position(|job| job.id == id)
+            .ok_or_else(|| format!("No interrupted job with ID {}", id))?;
+        let headers = merge_headers(&self.resumable[position].options.headers, headers)?;
+        let persisted = self.resumable.remove(position);
+        self.queue.push_front(id);
+        self.jobs.insert(id, Job {
+            id,
+            args: replace_header_args(&persisted.args, &headers),
+            options: DownloadOptions { headers, ..persisted.options },
+            save_dir: persisted.save_dir,
+            tmp_dir: persisted.tmp_dir,
+            history_id: persisted.history_id,
+            status: JobStatus::Queued,
+            progress: DownloadProgress::default(),
+            phases: Vec::new(),
+            exit_code: None,
+            error: None,
+            created_at: persisted.created_at,
+            finished_at: None,
+        });
+        self.persist();
+        Ok(())
+    }
+
+    pub fn discard_interrupted(&mut self, id: u64) -> Result<PersistedJob, String> {
+        let position = self.resumable
+            .iter()
+            .position(|job| job.id == id)
+            .ok_or_else(|| format!("No interrupted job with ID {}", id))?;
+        let persisted = self.resumable.remove(position);
+        self.persist();
+        Ok(persisted)
+    }
+
+    pub fn get(&self, id: u64) -> Option<&Job> {
+        self.jobs.get(&id)
+    }
+
+    // Whether `id` is a job of this session or an interrupted one
+    pub fn is_known(&self, id: u64) -> bool {
+        self.jobs.contains_key(&id) || self.resumable.iter().any(|job| job.id == id)
+    }
+
+    pub fn list(&self) -> Vec<Job> {
+        self.jobs.values().cloned().collect()
+    }
+
+    pub fn set_progress(&mut self, id: u64, progress: DownloadProgress) {
+        if let Some(job) = self.jobs.get_mut(&id) {
+            job.progress = progress;
+        }
+    }
+
+    // Records the phase a job has entered. Returns `None` when it was already in it.
+    pub fn set_phase(&mut self, id: u64, phase: DownloadPhase) -> Option<PhaseChange> {
+        let job = self.jobs.get_mut(&id)?;
+        if job.phases.last().map(|change| change.phase) == Some(phase) {
+            return None;
+        }
+        let change = PhaseChange { phase, started_at: now_millis() };
+        job.phases.push(change.clone());
+        Some(change)
+    }
+
+    pub fn set_history_id(&mut self, id: u64, history_id: u64) {
+        if let Some(job) = self.jobs.get_mut(&id) {
+            job.history_id = Some(history_id);
+        }
+        self.persist();
+    }
+
+    pub fn queue(&self) -> Vec<QueueEntry> {
+        self.queue.entries()
+    }
+
+    pub fn queue_mut(&mut self) -> &mut DownloadQueue {
+        &mut self.queue
+    }
+
+    // Takes queued jobs off the queue until `max_running` jobs are running and
+    // marks them running, so concurrent callers cannot start the same job twice
+    pub fn take_startable(&mut self, max_running: usize) -> Vec<u64> {
+        let mut running = self.jobs.values().filter(|job| job.status == JobStatus::Running).count();
+        let mut startable = Vec::new();
+        while running < max_running {
+            let Some(id) = self.queue.pop() else { break };
+            if let Some(job) = self.jobs.get_mut(&id) {
+                job.status = JobStatus::Running;
+                startable.push(id);
+                running += 1;
+            }
+        }
+        self.persist();
+        startable
+    }
+
+    // Puts a paused job back at the head of the queue
+    pub fn requeue(&mut self, id: u64) -> Result<(), String> {
+        let job = self.jobs.get_mut(&id).ok_or_else(|| format!("Unknown download job: {}", id))?;
+        if job.status != JobStatus::Paused {
+            return Err(format!("Job {} is not paused", id));
+        }
+        job.status = JobStatus::Queued;
+        self.queue.push_front(id);
+        self.persist();
+        Ok(())
+    }
+
+    pub fn attach_child(&mut self, id: u64, child: Child) {
+        if let Some(job) = self.jobs.get_mut(&id) {
+            job.exit_code = None;
+            job.error = None;
+            job.phases.clear();
+        }
+        self.children.insert(id, child);
+    }
+
+    // Returns the exit status once the job's process has ended, reaping it
+    pub fn poll_child(&mut self, id: u64) -> Option<io::Result<ExitStatus>> {
+        let result = match self.children.get_mut(&id) {
+            Some(child) => match child.try_wait() {
+                Ok(Some(status)) => Ok(status),
+                Ok(None) => return None,
+                Err(e) => Err(e),
+            },
+            None => Err(io::Error::new(io::ErrorKind::NotFound, "No process for job")),
+        };
+        self.children.remove(&id);
+        Some(result)
+    }
+
+    // Records the outcome of a process run. A job that was paused or cancelled
+    // keeps that status, since its process was stopped on purpose.
+    pub fn finish(&mut self, id: u64, exit_code: Option<i32>, error: Option<String>) -> Option<JobStatus> {
+        let job = self.jobs.get_mut(&id)?;
+        match job.status {
+            JobStatus::Paused => {}
+            JobStatus::Cancelled => job.finished_at = Some(now_millis()),
+            _ => {
+                job.status = if error.is_none() { JobStatus::Completed } else { JobStatus::Failed };
+                job.exit_code = exit_code;
+                job.error = error;
+                job.finished_at = Some(now_millis());
+            }
+        }
+        let status = job.status.clone();
+        self.persist();
+        Some(status)
+    }
+
+    // Stops the running process of a job and marks it with `status`.
+    // Returns whether a process was still attached to the job.
+    pub fn stop(&mut self, id: u64, status: JobStatus) -> Result<bool, String> {
+        let job = self.jobs.get_mut(&id).ok_or_else(|| format!("Unknown download job: {}", id))?;
+        match (&job.status, &status) {
+            (JobStatus::Running, _) => {}
+            (JobStatus::Paused | JobStatus::Queued, JobStatus::Cancelled) => {
+                self.queue.remove(id);
+            }
+            (current, _) => return Err(format!("Job {} cannot be stopped while {:?}", id, current)),
+        }
+        // Killed first so a process that survives keeps its job Running. The
+        // lock on the manager keeps `finish` from seeing the exit before the
+        // new status is set.
+        let running = match self.children.get(&id) {
+            Some(child) => {
+                kill_process_tree(child.id())?;
+                true
+            }
+            None => false,
+        };
+        if let Some(job) = self.jobs.get_mut(&id) {
+            job.status = status;
+        }
+        self.persist();
+        Ok(running)
+    }
+}
+
+// Swaps the `--header` pairs of a command line for `headers`, keeping the
+// place of the first one
+fn replace_header_args(args: &[String], headers: &[Header]) -> Vec<String> {
+    let mut replaced = Vec::with_capacity(args.len());
+    let mut position = None;
+    let mut rest = args.iter();
+    while let Some(arg) = rest.next() {
+        if arg == "--header" {
+            position.get_or_insert(replaced.len());
+            rest.next();
+        } else {
+            replaced.push(arg.clone());
+        }
+    }
+    let header_args = headers.iter().flat_map(|header| ["--header".to_string(), format!("{}: {}", header.key, header.value)]);
+    let position = position.unwrap_or(replaced.len().min(1));
+    replaced.splice(position..position, header_args);
+    replaced
+}
+
+pub fn now_millis() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis() as u64)
+        .unwrap_or(0)
+}
+
+// Puts the child in its own process group so `kill_process_tree` also reaches
+// the ffmpeg/mp4decrypt processes N_m3u8DL-RE spawns
+pub fn isolate_process_group(command: &mut Command) {
+    #[cfg(unix)]
+    {
+        use std::os::unix::process::CommandExt;
+        command.process_group(0);
+    }
+    #[cfg(not(unix))]
+    let _ = command;
+}
+
+pub fn kill_process_tree(pid: u32) -> Result<(), String> {
+    println!("Killing process tree of pid {}", pid);
+    let status = if cfg!(target_os = "windows") {
+        Command::new("taskkill")
+            .args(["/PID", &pid.to_string(), "/T", "/F"])
+            .status()
+    } else {
+        Command::new("kill")
+            .args(["-KILL", "--", &format!("-{}", pid)])
+            .status()
+    };
+    match status {
+        Ok(status) if status.success() => Ok(()),
+        Ok(status) => Err(format!("Failed to kill process {}: {:?}", pid, status)),
+        Err(e) => Err(format!("Failed to kill process {}: {}", pid, e)),
+    }
+}
+
+pub fn remove_segments_dir(job_id: u64, dir: &Path) {
+    if dir.exists() {
+        println!("Removing temporary files of job {}: {:?}", job_id, dir);
+        if let Err(e) = fs::remove_dir_all(dir) {
+            println!("Failed to remove {:?}: {:?}", dir, e);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn options() -> DownloadOptions {
+        serde_json::from_value(serde_json::json!({
+            "url": "https://example.com/master.m3u8",
+            "save_name": "video",
+            "headers": [],
+            "auto_merge": true,
+            "audio_only": false,
+        })).unwrap()
+    }
+
+    fn state_path(name: &str) -> PathBuf {
+        std::env::temp_dir().join(format!("m3u8-jobs-test-{}-{}-{}.json", name, std::process::id(), now_millis()))
+    }
+
+    #[test]
+    fn does_not_reuse_ids_after_a_restart() {
+        let path = state_path("ids");
+        let mut manager = JobManager::load(path.clone());
+        let first = manager.create(options(), Vec::new(), "/downloads".to_string(), "/tmp".to_string());
+        manager.finish(first, Some(0), None);
+
+        // Nothing is left to resume, the counter still remembers the ID
+        let mut manager = JobManager::load(path.clone());
+        assert!(manager.resumable().is_empty());
+        let second = manager.create(options(), Vec::new(), "/downloads".to_string(), "/tmp".to_string());
+        assert_eq!((first, second), (1, 2));
+        fs::remove_file(path).unwrap();
+    }
+
+    fn strings(args: &[&str]) -> Vec<String> {
+        args.iter().map(|arg| arg.to_string()).collect()
+    }
+
+    #[test]
+    fn keeps_credentials_out_of_the_state_file() {
+        let path = state_path("secrets");
+        let mut manager = JobManager::load(path.clone());
+        let mut with_headers = options();
+        with_headers.headers = vec![
+            Header { key: "Cookie".to_string(), value: "session=secret".to_string() },
+            Header { key: "Referer".to_string(), value: "https://example.com".to_string() },
+        ];
+        let args = strings(&["in.m3u8", "--header", "Cookie: session=secret", "--header", "Referer: https://example.com", "--tmp-dir", "/tmp"]);
+        let id = manager.create(with_headers, args, "/downloads".to_string(), "/tmp".to_string());
+        assert!(!fs::read_to_string(&path).unwrap().contains("secret"));
+
+        let mut manager = JobManager::load(path.clone());
+        let error = manager.resume_interrupted(id, Vec::new()).unwrap_err();
+        assert!(error.ends_with(": Cookie"), "{}", error);
+        assert_eq!(manager.resumable().len(), 1);
+
+        manager.resume_interrupted(id, vec![Header { key: "cookie".to_string(), value: "session=new".to_string() }]).unwrap();
+        assert_eq!(manager.get(id).unwrap().args, strings(&[
+            "in.m3u8", "--header", "Referer: https://example.com", "--header", "cookie: session=new", "--tmp-dir", "/tmp",
+        ]));
+        fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn reads_the_old_state_layout() {
+        let path = state_path("legacy");
+        let job = serde_json::json!({
+            "id": 4,
+            "options": serde_json::to_value(options()).unwrap(),
+            "args": [],
+            "save_dir": "/downloads",
+            "tmp_dir": "/tmp",
+            "history_id": null,
+            "created_at": 0,
+        });
+        fs::write(&path, serde_json::json!([job]).to_string()).unwrap();
+
+        let mut manager = JobManager::load(path.clone());
+        assert_eq!(manager.resumable().len(), 1);
+        assert_eq!(manager.create(options(), Vec::new(), "/downloads".to_string(), "/tmp".to_string()), 5);
+        fs::remove_file(path).unwrap();
+    }
+}
@@ -0,0 +1,277 @@
+use std::collections::{HashMap, VecDeque};
+use std::fs::{self, File, OpenOptions};
+use std::io::Write;
+use std::path::PathBuf;
+use serde::Serialize;
+
+use crate::jobs::now_millis;
+use crate::progress::{parse_line, strip_ansi};
+
+// Lines of output kept in memory per job. Progress rows are redrawn many times
+// a second, so only the newest of consecutive ones is kept. The log file has
+// every line.
+pub const MAX_LOG_LINES: usize = 5000;
+
+#[derive(Debug, Serialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum LogSource {
+    Stdout,
+    Stderr,
+    // Written by the app itself, e.g. the command line of each run
+    App,
+}
+
+impl LogSource {
+    fn tag(self) -> &'static str {
+        match self {
+            LogSource::Stdout => "stdout",
+            LogSource::Stderr => "stderr",
+            LogSource::App => "app",
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Clone, PartialEq)]
+pub struct LogLine {
+    // Position of the line in the job's log, counting from 0
+    pub offset: u64,
+    pub source: LogSource,
+    // Milliseconds since the Unix epoch
+    pub timestamp: u64,
+    pub text: String,
+}
+
+// What `get_job_log` returns: the lines from the requested offset on
+#[derive(Debug, Serialize, Clone, PartialEq)]
+pub struct LogChunk {
+    pub lines: Vec<LogLine>,
+    // Offset to ask for next time to get only new lines
+    pub next_offset: u64,
+    // Lines at or after the requested offset that fell out of memory, not
+    // counting collapsed progress rows
+    pub dropped: u64,
+    pub path: Option<String>,
+}
+
+#[derive(Debug, Default)]
+struct JobLog {
+    lines: VecDeque<LogLine>,
+    next_offset: u64,
+    file: Option<File>,
+    path: Option<PathBuf>,
+    // Whether the newest line in memory is a progress row
+    last_is_progress: bool,
+}
+
+// Combined output of every job, bounded in memory and written in full to
+// `<dir>/job-<id>.log`. Resumed jobs keep their ID and append to the same file.
+#[derive(Debug, Default)]
+pub struct JobLogs {
+    dir: Option<PathBuf>,
+    logs: HashMap<u64, JobLog>,
+}
+
+impl JobLogs {
+    pub fn new(dir: PathBuf) -> Self {
+        JobLogs { dir: Some(dir), logs: HashMap::new() }
+    }
+
+    pub fn path(&self, job_id: u64) -> Option<PathBuf> {
+        self.dir.as_ref().map(|dir| dir.join(format!("job-{}.log", job_id)))
+    }
+
+    // Starts an empty log for a new job, replacing whatever an earlier job
+    // with the same ID left behind
+    pub fn start_job(&mut self, job_id: u64) {
+        let path = self.path(job_id);
+        let file = path.as_ref().and_then(|path| open_log_file(path, true));
+        self.logs.insert(job_id, JobLog { file, path, ..JobLog::default() });
+    }
+
+    // Marks the start of a new run of N_m3u8DL-RE in the log
+    pub fn start_run(&mut self, job_id: u64, command: &str) {
+        self.push(job_id, LogSource::App, &format!("Running {}", command));
+    }
+
+    pub fn push(&mut self, job_id: u64, source: LogSource, text: &str) {
+        let path = self.path(job_id);
+        let log = self.logs.entry(job_id).or_default();
+        if log.path.is_none() {
+            log.path = path;
+            log.file = log.path.as_ref().and_then(|path| open_log_file(path, false));
+        }
+
+        let line = LogLine { offset: log.next_offset, source, timestamp: now_millis(), text: strip_ansi(text) };
+        if let Some(file) = &mut log.file {
+            if let Err(e) = writeln!(file, "{} [{}] {}", line.timestamp, source.tag(), line.text) {
+                println!("Failed to write log of job {}: {}", job_id, e);
+                log.file = None;
+            }
+        }
+        log.next_offset += 1;
+        let is_progress = source == LogSource::Stdout && parse_line(text).is_some();
+        if is_progress && log.last_is_progress {
+            log.lines.pop_back();
+        }
+        log.last_is_progress = is_progress;
+        log.lines.push_back(line);
+        if log.lines.len() > MAX_LOG_LINES {
+            log.lines.pop_front();
+        }
+    }
+
+    // Lines from `offset` on. Jobs without output yet give an empty chunk.
+    pub fn read(&self, job_id: u64, offset: u64) -> LogChunk {
+        let Some(log) = self.logs.get(&job_id) else {
+            return LogChunk {
+                lines: Vec::new(),
+                next_offset: offset,
+                dropped: 0,
+                path: self.path(job_id).filter(|path| path.exists()).map(|path| path.display().to_string()),
+            };
+        };
+        let first_offset = log.lines.front().map_or(log.next_offset, |line| line.offset);
+        LogChunk {
+            lines: log.lines.iter().filter(|line| line.offset >= offset).cloned().collect(),
+            next_offset: log.next_offset.max(offset),
+            dropped: first_offset.saturating_sub(offset),
+            path: log.path.as_ref().map(|path| path.display().to_string()),
+        }
+    }
+
+    // The last thing the current run wrote to stderr, to explain a failure
+    pub fn last_error(&self, job_id: u64) -> Option<String> {
+        self.logs.get(&job_id)?
+            .lines
+            .iter()
+            .rev()
+            .take_while(|line| line.source != LogSource::App)
+            .find(|line| line.source == LogSource::Stderr && !line.text.trim().is_empty())
+            .map(|line| line.text.trim().to_string())
+    }
+}
+
+// Opens a job's log file to append to, or emptied first when `truncate` is set
+fn open_log_file(path: &PathBuf, truncate: bool) -> Option<File> {
+    let open = || {
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        let mut options = OpenOptions::new();
+        if truncate {
+            options.write(true).truncate(true);
+        } else {
+            options.append(true);
+        }
+        options.create(true).open(path)
+    };
+    match open() {
+        Ok(file) => Some(file),
+        Err(e) => {
+            println!("Failed to open log file {:?}: {}", path, e);
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn texts(chunk: &LogChunk) -> Vec<&str> {
+        chunk.lines.iter().map(|line| line.text.as_str()).collect()
+    }
+
+    #[test]
+    fn reads_from_an_offset() {
+        let mut logs = JobLogs::default();
+        logs.push(1, LogSource::Stdout, "\x1b[32mINFO\x1b[0m : Loading URL");
+        logs.push(1, LogSource::Stderr, "oops");
+        logs.push(2, LogSource::Stdout, "other job");
+
+        let chunk = logs.read(1, 0);
+        assert_eq!(texts(&chunk), ["INFO : Loading URL", "oops"]);
+        assert_eq!((chunk.next_offset, chunk.dropped), (2, 0));
+        assert_eq!(chunk.lines[1].source, LogSource::Stderr);
+
+        logs.push(1, LogSource::Stdout, "Done");
+        let chunk = logs.read(1, 2);
+        assert_eq!(texts(&chunk), ["Done"]);
+        assert_eq!(logs.read(1, 3).lines, []);
+        assert_eq!(logs.read(3, 0), LogChunk { lines: Vec::new(), next_offset: 0, dropped: 0, path: None });
+    }
+
+    #[test]
+    fn keeps_only_the_newest_lines() {
+        let mut logs = JobLogs::default();
+        for line in 0..MAX_LOG_LINES + 10 {
+            logs.push(1, LogSource::Stdout, &line.to_string());
+        }
+        let chunk = logs.read(1, 4);
+        assert_eq!(chunk.lines.len(), MAX_LOG_LINES);
+        assert_eq!(chunk.lines[0].offset, 10);
+        assert_eq!(chunk.dropped, 6);
+        assert_eq!(chunk.next_offset, (MAX_LOG_LINES + 10) as u64);
+    }
+
+    #[test]
+    fn keeps_only_the_newest_of_consecutive_progress_rows() {
+        let mut logs = JobLogs::default();
+        logs.start_run(1, "N_m3u8DL-RE a.m3u8");
+        logs.push(1, LogSource::Stdout, "INFO : Start downloading...");
+        let row = |done: usize| format!("Vid 1280x720 | 2500 Kbps ━━━━━━━━━━ {}/20000 {:.2}% - - --:--:--", done, done as f64 / 200.0);
+        for done in 1..=MAX_LOG_LINES * 2 {
+            logs.push(1, LogSource::Stdout, &row(done));
+        }
+        logs.push(1, LogSource::Stderr, "warning");
+        logs.push(1, LogSource::Stdout, &row(MAX_LOG_LINES * 2 + 1));
+
+        let chunk = logs.read(1, 0);
+        let newest = row(MAX_LOG_LINES * 2);
+        let last = row(MAX_LOG_LINES * 2 + 1);
+        assert_eq!(texts(&chunk), ["Running N_m3u8DL-RE a.m3u8", "INFO : Start downloading...", newest.as_str(), "warning", last.as_str()]);
+        assert_eq!(chunk.dropped, 0);
+        // Offsets still count every line
+        assert_eq!(chunk.next_offset, (MAX_LOG_LINES * 2 + 4) as u64);
+        assert_eq!(chunk.lines[2].offset, (MAX_LOG_LINES * 2 + 1) as u64);
+    }
+
+    #[test]
+    fn finds_the_last_error_of_the_current_run() {
+        let mut logs = JobLogs::default();
+        logs.start_run(1, "N_m3u8DL-RE a.m3u8");
+        logs.push(1, LogSource::Stderr, "first run failed");
+        logs.start_run(1, "N_m3u8DL-RE a.m3u8");
+        logs.push(1, LogSource::Stdout, "INFO : Loading URL");
+        assert_eq!(logs.last_error(1), None);
+        logs.push(1, LogSource::Stderr, "Unhandled exception. System.IO.IOException: Disk full ");
+        logs.push(1, LogSource::Stdout, "");
+        assert_eq!(logs.last_error(1).as_deref(), Some("Unhandled exception. System.IO.IOException: Disk full"));
+    }
+
+    #[test]
+    fn writes_every_line_to_the_log_file() {
+        let dir = std::env::temp_dir().join(format!("m3u8-logs-test-{}-{}", std::process::id(), now_millis()));
+        let mut logs = JobLogs::new(dir.clone());
+        logs.start_run(7, "N_m3u8DL-RE a.m3u8");
+        logs.push(7, LogSource::Stdout, "\x1b[1mVid\x1b[0m 1/2");
+        // A resumed job appends to the same file
+        let mut logs = JobLogs::new(dir.clone());
+        logs.push(7, LogSource::Stderr, "error");
+
+        let tagged = |contents: String| -> Vec<String> {
+            contents.lines().map(|line| line.split_once(' ').unwrap().1.to_string()).collect()
+        };
+        let contents = fs::read_to_string(dir.join("job-7.log")).unwrap();
+        assert_eq!(tagged(contents), ["[app] Running N_m3u8DL-RE a.m3u8", "[stdout] Vid 1/2", "[stderr] error"]);
+        assert_eq!(logs.read(7, 0).path, Some(dir.join("job-7.log").display().to_string()));
+
+        // A new job under the same ID starts over
+        logs.start_job(7);
+        logs.start_run(7, "N_m3u8DL-RE b.m3u8");
+        assert_eq!(texts(&logs.read(7, 0)), ["Running N_m3u8DL-RE b.m3u8"]);
+        let contents = fs::read_to_string(dir.join("job-7.log")).unwrap();
+        assert_eq!(tagged(contents), ["[app] Running N_m3u8DL-RE b.m3u8"]);
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
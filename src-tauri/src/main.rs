@@ -1,7 +1,19 @@
 #![cfg_attr(not(debug_assertions), windows_subsystem = "windows")]
 
-use std::process::Command;
-use std::path::PathBuf;
+mod args;
+mod discovery;
+mod history;
+mod jobs;
+mod logs;
+mod progress;
+mod queue;
+mod selection;
+mod settings;
+mod streams;
+
+use std::io::Read;
+use std::process::{Child, Command, Output, Stdio};
+use std::path::{Path, PathBuf};
 use std::fs;
 use tauri::{
     api::path,
@@ -10,19 +22,39 @@ use tauri::{
     State,
 };
 use serde::{Deserialize, Serialize};
-use regex::Regex;
-use std::sync::{OnceLock, Mutex};
+use std::sync::Mutex;
+use std::thread;
+use std::time::{Duration, Instant};
+use args::{MuxFormat, MuxOptions, N_m3u8DLArgs};
+use discovery::{DiscoveryError, DiscoveryErrorKind, DiscoveryMode};
+use discovery::probes::{CancelToken, Probes};
+use history::{History, HistoryPage, HistoryQuery};
+use jobs::{Job, JobEvent, JobManager, JobStatus, PersistedJob};
+use logs::{JobLogs, LogChunk, LogSource};
+use progress::{DownloadPhase, DownloadProgress, DownloadSummary, OutputLines, PhaseChange, ProgressReader, ProgressSink};
+use queue::QueueEntry;
+use selection::SelectionRules;
+use settings::{apply_settings, load_settings, save_settings, ProfileList, Settings, SettingsStore};
+use streams::{AudioStream, StreamInfo, SubtitleStream, VideoStream};
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
 struct DownloadOptions {
     url: String,
     save_name: String,
     headers: Vec<Header>,
-    video_quality: Option<StreamOption>,
-    audio_stream: Option<StreamOption>,
-    subtitles: Option<StreamOption>,
+    #[serde(default, deserialize_with = "streams::deserialize_stored")]
+    video_quality: Option<VideoStream>,
+    #[serde(default, alias = "audio_stream", deserialize_with = "streams::deserialize_stored_list")]
+    audio_streams: Vec<AudioStream>,
+    #[serde(default, deserialize_with = "streams::deserialize_stored_list")]
+    subtitles: Vec<SubtitleStream>,
     auto_merge: bool,
     audio_only: bool,
+    #[serde(default)]
+    priority: i32,
+    // Settings profile to download with instead of the active one
+    #[serde(default)]
+    profile: Option<String>,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -31,102 +63,14 @@ struct Header {
     value: String,
 }
 
-#[derive(Debug, Serialize, Deserialize, Clone)]
-struct StreamOption {
-    description: String,
-}
-
-#[derive(Debug, Serialize, Deserialize, Clone, Default)]
-struct Settings {
-    download_location: Option<String>,
-    default_format: Option<String>,
-    auto_start: Option<bool>,
-    concurrent_downloads: Option<String>,
-    timeout: Option<String>,
-    debug_mode: Option<bool>,
-    tmp_dir: Option<String>,
-    thread_count: Option<String>,
-    download_retry_count: Option<String>,
-    check_segments_count: Option<bool>,
-    binary_merge: Option<bool>,
-    use_ffmpeg_concat_demuxer: Option<bool>,
-    del_after_done: Option<bool>,
-    no_date_info: Option<bool>,
-    no_log: Option<bool>,
-    write_meta_json: Option<bool>,
-    append_url_params: Option<bool>,
-    concurrent_download: Option<bool>,
-    sub_only: Option<bool>,
-    sub_format: Option<String>,
-    auto_subtitle_fix: Option<bool>,
-    log_level: Option<String>,
-    use_system_proxy: Option<bool>,
-}
-
-#[derive(Debug, Serialize, Deserialize, Clone)]
-struct StreamInfo {
-    video_streams: Vec<VideoStream>,
-    audio_streams: Vec<AudioStream>,
-    subtitle_streams: Vec<SubtitleStream>,
-}
-
-#[derive(Debug, Serialize, Deserialize, Clone)]
-struct VideoStream {
-    resolution: String,
-    bitrate: String,
-    fps: String,
-    codec: String,
-}
-
-#[derive(Debug, Serialize, Deserialize, Clone)]
-struct AudioStream {
-    id: String,
-    name: String,
-    language: String,
-    channels: String,
-}
-
-#[derive(Debug, Serialize, Deserialize, Clone)]
-struct SubtitleStream {
-    id: String,
-    name: String,
-    language: String,
-}
-
-#[derive(Debug, Serialize, Clone, Default)]
-struct DownloadProgress {
-    video_progress: ProgressInfo,
-    audio_progress: ProgressInfo,
-    subtitle_progress: ProgressInfo,
-}
-
-#[derive(Debug, Serialize, Clone, Default)]
-struct ProgressInfo {
-    current: i32,
-    total: i32,
-    percentage: f32,
-    downloaded: String,
-    total_size: String,
-    speed: String,
-    eta: String,
-}
-
-enum StreamType {
-    Video,
-    Audio,
-    Subtitle,
-}
-
-// Add regex patterns as static variables
-static PROGRESS_PATTERN: OnceLock<Regex> = OnceLock::new();
-
-fn get_progress_pattern() -> &'static Regex {
-    PROGRESS_PATTERN.get_or_init(|| {
-        // Match format like: "Vid 960x540 | 2168 Kbps | 60 ------------------------------ 36/101 35.64% 56.60MB/163.34MB 5.12MBps 00:00:27"
-        Regex::new(
-            r"(?i)(?:Vid|Aud|Sub).*?[-]+\s+(\d+)/(\d+)\s+(\d+\.?\d*)%\s+((?:[\d\.]+(?:KB|MB)(?:/[\d\.]+MB)?)|[-\s]+)\s+((?:[\d\.]+(?:KB|MB)ps)|[-\s]+)\s+(\d+:\d+:\d+|--:--:--)"
-        ).unwrap()
-    })
+#[derive(Debug, Deserialize, Clone, Default)]
+struct RedownloadOverrides {
+    save_name: Option<String>,
+    // Replaces recorded headers by name, e.g. to fill in redacted cookies.
+    // A header with an empty value is dropped.
+    headers: Option<Vec<Header>>,
+    profile: Option<String>,
+    settings: Option<Settings>,
 }
 
 fn get_bin_dir() -> PathBuf {
@@ -161,48 +105,19 @@ fn get_bin_dir() -> PathBuf {
     bin_dir
 }
 
-fn get_settings_path() -> PathBuf {
+fn get_history_path() -> PathBuf {
     let app_dir = path::app_data_dir(&tauri::Config::default()).unwrap();
-    println!("Settings directory: {:?}", app_dir);
-    let settings_path = app_dir.join("settings.json");
-    println!("Settings file path: {:?}", settings_path);
-    settings_path
-}
-
-fn load_settings() -> Settings {
-    let settings_path = get_settings_path();
-    println!("Loading settings from: {:?}", settings_path);
-    if settings_path.exists() {
-        if let Ok(contents) = fs::read_to_string(&settings_path) {
-            println!("Settings file contents: {}", contents);
-            if let Ok(settings) = serde_json::from_str(&contents) {
-                println!("Successfully loaded settings: {:?}", settings);
-                return settings;
-            }
-        }
-    }
-    println!("No settings found, using defaults");
-    Settings::default()
+    app_dir.join("history.json")
 }
 
-fn save_settings(settings: &Settings) -> Result<(), String> {
-    let settings_path = get_settings_path();
-    println!("Saving settings to: {:?}", settings_path);
-    if let Some(parent) = settings_path.parent() {
-        fs::create_dir_all(parent).map_err(|e| {
-            println!("Failed to create settings directory: {:?}", e);
-            e.to_string()
-        })?;
-    }
-    let contents = serde_json::to_string_pretty(settings).map_err(|e| {
-        println!("Failed to serialize settings: {:?}", e);
-        e.to_string()
-    })?;
-    println!("Settings to save: {}", contents);
-    fs::write(&settings_path, contents).map_err(|e| {
-        println!("Failed to write settings file: {:?}", e);
-        e.to_string()
-    })
+fn get_jobs_state_path() -> PathBuf {
+    let app_dir = path::app_data_dir(&tauri::Config::default()).unwrap();
+    app_dir.join("jobs.json")
+}
+
+fn get_logs_dir() -> PathBuf {
+    let app_dir = path::app_data_dir(&tauri::Config::default()).unwrap();
+    app_dir.join("logs")
 }
 
 fn get_platform_binary_name(name: &str) -> String {
@@ -243,273 +158,676 @@ async fn check_required_binaries(window: Window) -> Result<(), String> {
 }
 
 #[tauri::command]
-async fn start_download(window: Window, options: DownloadOptions, state: State<'_, Mutex<Settings>>) -> Result<(), String> {
-    println!("Starting download with options: {:?}", options);
-    
-    let bin_dir = get_bin_dir();
-    let binary_path = bin_dir.join(get_platform_binary_name("N_m3u8DL-RE"));
-    println!("Binary path: {:?}", binary_path);
-    
-    let mut args = vec![
-        options.url,
-        "--save-name".to_string(),
-        options.save_name,
-    ];
-    println!("Initial args: {:?}", args);
+async fn start_download(window: Window, options: DownloadOptions, state: State<'_, Mutex<SettingsStore>>) -> Result<u64, String> {
+    let settings = state.lock().unwrap().profile(options.profile.as_deref())?.clone();
+    let options = resolve_streams_in_background(&window, options, &settings).await?;
+    Ok(enqueue_download(&window, options, &settings))
+}
+
+// Stream discovery fetches the manifest or runs N_m3u8DL-RE, so it runs on a
+// blocking thread instead of holding up the async runtime
+async fn resolve_streams_in_background(window: &Window, options: DownloadOptions, settings: &Settings) -> Result<DownloadOptions, String> {
+    let (window, settings) = (window.clone(), settings.clone());
+    tauri::async_runtime::spawn_blocking(move || resolve_streams(&window, options, &settings))
+        .await
+        .map_err(|e| format!("Stream discovery stopped: {}", e))?
+}
+
+// Fills in the streams of a download that arrives without any selection from
+// the profile's selection rules, and checks that the picked video stream is
+// still in the manifest. Without the check a stale selection matches nothing
+// and N_m3u8DL-RE falls back to asking on its console, where nobody answers.
+fn resolve_streams(window: &Window, options: DownloadOptions, settings: &Settings) -> Result<DownloadOptions, String> {
+    if options.video_quality.is_none() && selection_rules(&options, settings).is_none() {
+        return Ok(options);
+    }
+    let stream_info = discover_streams(window, &options.url, &options.headers, settings, false)?;
+    apply_streams(options, settings, &stream_info)
+}
+
+// The profile's selection rules, if they apply to this download
+fn selection_rules<'a>(options: &DownloadOptions, settings: &'a Settings) -> Option<&'a SelectionRules> {
+    let nothing_selected = options.video_quality.is_none() && options.audio_streams.is_empty() && options.subtitles.is_empty();
+    settings.selection_rules.as_ref().filter(|_| nothing_selected)
+}
+
+fn apply_streams(mut options: DownloadOptions, settings: &Settings, stream_info: &StreamInfo) -> Result<DownloadOptions, String> {
+    if let Some(rules) = selection_rules(&options, settings) {
+        let selection = rules.resolve(stream_info)?;
+        println!("Selection rules picked: {:?}", selection);
+        options.video_quality = selection.video;
+        options.audio_streams = selection.audio_streams;
+        options.subtitles = selection.subtitles;
+    }
+
+    if let Some(wanted) = &options.video_quality {
+        let Some(video) = streams::find_video(&stream_info.video_streams, wanted) else {
+            return Err(format!(
+                "The selected video stream ({}) is not among the {} streams of the manifest, load the stream list again",
+                wanted.label(),
+                stream_info.video_streams.len(),
+            ));
+        };
+        options.video_quality = Some(video.clone());
+    }
+    Ok(options)
+}
+
+// Builds the N_m3u8DL-RE arguments for a download and queues it as a new job
+fn enqueue_download(window: &Window, options: DownloadOptions, settings: &Settings) -> u64 {
+    println!("Starting download with options: {:?}", DownloadOptions { headers: history::redact_headers(&options.headers), ..options.clone() });
+    let args = build_download_args(&options, settings);
+    let save_dir = args.save_dir.clone().unwrap_or_default();
+    let tmp_dir = args.tmp_dir.clone().unwrap_or_default();
+
+    let job_id = window.state::<Mutex<JobManager>>()
+        .lock()
+        .unwrap()
+        .create(options, args.to_args(), save_dir, tmp_dir);
+    window.state::<Mutex<JobLogs>>().lock().unwrap().start_job(job_id);
+    println!("Queued download job {}", job_id);
+
+    if queue_auto_starts(window) {
+        process_queue(window);
+    } else {
+        println!("Auto-start is off, job {} waits for the queue to be started", job_id);
+        emit_queue_changed(window);
+    }
+
+    job_id
+}
+
+fn build_download_args(options: &DownloadOptions, settings: &Settings) -> N_m3u8DLArgs {
+    let mut args = N_m3u8DLArgs::new(options.url.clone())
+        .save_name(options.save_name.clone());
 
     // Add headers
-    for header in options.headers {
-        args.push("--header".to_string());
-        args.push(format!("{}: {}", header.key, header.value));
+    for header in &options.headers {
+        args = args.header(header.key.clone(), header.value.clone());
     }
-    println!("Args after adding headers: {:?}", args);
 
     // Add stream selection options
-    if let Some(video_quality) = options.video_quality {
-        println!("Processing video quality: {:?}", video_quality);
-        let parts: Vec<&str> = video_quality.description.split(" | ").collect();
-        if parts.len() >= 4 {
-            let resolution = parts[0];
-            let bitrate = parts[1];
-            let fps = parts[2];
-            let codec = parts[3];
-            let bw = bitrate.parse::<i32>().unwrap_or(0);
-            args.push("-sv".to_string());
-            args.push(format!("res={}:bwMin={}:bwMax={}:frame={}:codecs={}", 
-                resolution, bw-1, bw+1, fps, codec));
-            println!("Added video quality args: {:?}", &args[args.len()-2..]);
-        } else {
-            println!("Warning: Invalid video quality format: {:?}", parts);
+    if let Some(video) = &options.video_quality {
+        println!("Selecting video stream: {:?}", video);
+        match video.selector() {
+            Some(selector) => args = args.select_video(selector),
+            None => println!("Warning: Video stream {} has nothing to select it by", video.id),
         }
     }
 
-    if let Some(audio_stream) = options.audio_stream {
-        println!("Processing audio stream: {:?}", audio_stream);
-        let parts: Vec<&str> = audio_stream.description.split(" | ").collect();
-        if parts.len() >= 4 {
-            let id = parts[0];
-            let name = parts[1];
-            let lang = parts[2];
-            let ch = parts[3];
-            args.push("-sa".to_string());
-            args.push(format!("id={}:name={}:lang={}:ch={}", id, name, lang, ch));
-            println!("Added audio stream args: {:?}", &args[args.len()-2..]);
-        } else {
-            println!("Warning: Invalid audio stream format: {:?}", parts);
+    if !options.audio_streams.is_empty() {
+        println!("Selecting audio streams: {:?}", options.audio_streams);
+        match streams::selector(&options.audio_streams) {
+            Some(selector) => args = args.select_audio(selector),
+            None => println!("Warning: Selected audio streams have nothing to select them by"),
         }
     }
 
-    if let Some(subtitles) = options.subtitles {
-        println!("Processing subtitles: {:?}", subtitles);
-        let parts: Vec<&str> = subtitles.description.split(" | ").collect();
-        if parts.len() >= 3 {
-            let id = parts[0];
-            let name = parts[1];
-            let lang = parts[2];
-            args.push("-ss".to_string());
-            args.push(format!("id={}:name={}:lang={}", id, name, lang));
-            println!("Added subtitle args: {:?}", &args[args.len()-2..]);
-        } else {
-            println!("Warning: Invalid subtitle format: {:?}", parts);
+    if !options.subtitles.is_empty() {
+        println!("Selecting subtitle streams: {:?}", options.subtitles);
+        match streams::selector(&options.subtitles) {
+            Some(selector) => args = args.select_subtitle(selector),
+            None => println!("Warning: Selected subtitle streams have nothing to select them by"),
         }
     }
 
     // Add muxing options
     if options.auto_merge {
         println!("Adding muxing options (auto_merge: true, audio_only: {})", options.audio_only);
-        args.push("-M".to_string());
-        args.push(format!("format=mkv:muxer=ffmpeg:bin_path=auto:skip_sub={}:keep=true", 
-            options.audio_only));
-        println!("Added muxing args: {:?}", &args[args.len()-2..]);
-    }
-
-    // Add other options from settings
-    let settings = state.lock().unwrap();
-
-    args.extend_from_slice(&[
-        "--save-dir".to_string(),
-        settings.download_location.clone().unwrap_or_else(|| {
-            path::download_dir().unwrap().join("m3u8").to_string_lossy().into_owned()
-        }),
-        "--tmp-dir".to_string(),
-        settings.tmp_dir.clone().unwrap_or_else(|| {
-            path::download_dir().unwrap().join("m3u8/Temp").to_string_lossy().into_owned()
-        }),
-        "--thread-count".to_string(),
-        settings.thread_count.clone().unwrap_or_else(|| "16".to_string()),
-        "--download-retry-count".to_string(),
-        settings.download_retry_count.clone().unwrap_or_else(|| "3".to_string()),
-        "--sub-format".to_string(),
-        settings.sub_format.clone().unwrap_or_else(|| "SRT".to_string()),
-        "--log-level".to_string(),
-        settings.log_level.clone().unwrap_or_else(|| "INFO".to_string()),
-        "--force-ansi-console".to_string(),
-    ]);
-    println!("Final args: {:?}", args);
-
-    println!("Executing command: {} {}", binary_path.display(), args.join(" "));
+        args = args.mux_after_done(MuxOptions {
+            format: settings.default_format.unwrap_or(MuxFormat::Mkv),
+            skip_sub: options.audio_only,
+            ..MuxOptions::default()
+        });
+    }
+
+    let args = apply_settings(args, settings);
+    println!("Final args: {:?}", history::redact_args(&args.to_args()));
+    args
+}
+
+// Starts queued jobs while fewer than `concurrent_downloads` are running
+fn process_queue(window: &Window) {
+    let max_running = window.state::<Mutex<SettingsStore>>().lock().unwrap().active().max_concurrent_downloads();
+    let manager = window.state::<Mutex<JobManager>>();
+    let startable = manager.lock().unwrap().take_startable(max_running);
+
+    for job_id in startable {
+        let args = match manager.lock().unwrap().get(job_id) {
+            Some(job) => job.args.clone(),
+            None => continue,
+        };
+        println!("Starting queued download job {}", job_id);
+        if let Err(e) = spawn_job(window, job_id, &args) {
+            manager.lock().unwrap().finish(job_id, None, Some(e.clone()));
+            if let Err(e) = window.emit("download-error", JobEvent { job_id, data: e }) {
+                println!("Error emitting job {} result: {:?}", job_id, e);
+            }
+        }
+    }
+
+    emit_queue_changed(window);
+}
+
+// The queue is shared by every profile, so the active one decides
+fn queue_auto_starts(window: &Window) -> bool {
+    window.state::<Mutex<SettingsStore>>().lock().unwrap().active().auto_start_enabled()
+}
+
+// The first run of a job opens its history entry; resumed runs keep using it
+fn record_history_start(window: &Window, job_id: u64) {
+    let manager = window.state::<Mutex<JobManager>>();
+    let job = match manager.lock().unwrap().get(job_id) {
+        Some(job) if job.history_id.is_none() => job.clone(),
+        _ => return,
+    };
+    match window.state::<Mutex<History>>().lock().unwrap().record_start(&job) {
+        Ok(history_id) => manager.lock().unwrap().set_history_id(job_id, history_id),
+        Err(e) => println!("Failed to record history for job {}: {}", job_id, e),
+    }
+}
+
+fn record_history_finish(window: &Window, job_id: u64) {
+    let job = match window.state::<Mutex<JobManager>>().lock().unwrap().get(job_id) {
+        Some(job) => job.clone(),
+        None => return,
+    };
+    if let Some(history_id) = job.history_id {
+        if let Err(e) = window.state::<Mutex<History>>().lock().unwrap().record_finish(history_id, &job) {
+            println!("Failed to record history for job {}: {}", job_id, e);
+        }
+    }
+}
+
+fn emit_queue_changed(window: &Window) {
+    let entries = window.state::<Mutex<JobManager>>().lock().unwrap().queue();
+    if let Err(e) = window.emit("queue-changed", entries) {
+        println!("Error emitting queue-changed event: {:?}", e);
+    }
+}
+
+fn emit_progress(window: &Window, job_id: u64, progress: &DownloadProgress) {
+    if let Err(e) = window.emit("download-progress", JobEvent { job_id, data: progress.clone() }) {
+        eprintln!("Failed to emit progress: {:?}", e);
+    }
+}
+
+// Reports what a running job's output says to the job manager and the frontend
+struct JobProgressSink<'a> {
+    window: &'a Window,
+    job_id: u64,
+}
+
+impl ProgressSink for JobProgressSink<'_> {
+    fn set_phase(&mut self, phase: DownloadPhase) -> Option<PhaseChange> {
+        self.window.state::<Mutex<JobManager>>().lock().unwrap().set_phase(self.job_id, phase)
+    }
+
+    fn send_phase(&mut self, change: PhaseChange) {
+        println!("Download {} is {:?}", self.job_id, change.phase);
+        if let Err(e) = self.window.emit("download-phase", JobEvent { job_id: self.job_id, data: change }) {
+            eprintln!("Failed to emit phase: {:?}", e);
+        }
+    }
+
+    fn set_progress(&mut self, progress: &DownloadProgress) {
+        self.window.state::<Mutex<JobManager>>().lock().unwrap().set_progress(self.job_id, progress.clone());
+    }
+
+    fn send_progress(&mut self, progress: &DownloadProgress) {
+        emit_progress(self.window, self.job_id, progress);
+    }
+}
+
+// Runs N_m3u8DL-RE for a registered job and reports its output and result
+// through job-tagged events. Used both for new downloads and resumed ones.
+fn spawn_job(window: &Window, job_id: u64, args: &[String]) -> Result<(), String> {
+    let bin_dir = get_bin_dir();
+    let binary_path = bin_dir.join(get_platform_binary_name("N_m3u8DL-RE"));
+
+    // Header values may carry credentials
+    let command_line = format!("{} {}", binary_path.display(), history::redact_args(args).join(" "));
+    println!("Executing command: {}", command_line);
+    let logs = window.state::<Mutex<JobLogs>>();
+    logs.lock().unwrap().start_run(job_id, &command_line);
     
     // Use spawn to capture output in real-time
-    let mut child = Command::new(binary_path)
-        .args(&args)
+    let mut command = Command::new(binary_path);
+    command
+        .args(args)
         .stdout(std::process::Stdio::piped())
-        .stderr(std::process::Stdio::piped())
+        .stderr(std::process::Stdio::piped());
+    jobs::isolate_process_group(&mut command);
+    let mut child = command
         .spawn()
         .map_err(|e| {
             println!("Command execution error: {:?}", e);
+            logs.lock().unwrap().push(job_id, LogSource::App, &format!("Failed to start: {}", e));
             e.to_string()
         })?;
 
     let stdout = child.stdout.take().unwrap();
     let stderr = child.stderr.take().unwrap();
 
+    let manager = window.state::<Mutex<JobManager>>();
+    let (progress, profile) = manager.lock()
+        .unwrap()
+        .get(job_id)
+        .map(|job| (job.progress.clone(), job.options.profile.clone()))
+        .unwrap_or_default();
+    // The job's profile may have been deleted since it was queued
+    let mut reader = {
+        let store = window.state::<Mutex<SettingsStore>>();
+        let store = store.lock().unwrap();
+        let settings = store.profile(profile.as_deref()).unwrap_or_else(|_| store.active());
+        ProgressReader::new(progress, settings.progress_interval())
+    };
+    manager.lock().unwrap().attach_child(job_id, child);
+    record_history_start(window, job_id);
+
     let stdout_reader = std::io::BufReader::new(stdout);
     let stderr_reader = std::io::BufReader::new(stderr);
 
-    let mut current_progress = DownloadProgress::default();
+    let window_clone = window.clone();
+
+    let stdout_thread = std::thread::spawn(move || {
+        let logs = window_clone.state::<Mutex<JobLogs>>();
+        let mut sink = JobProgressSink { window: &window_clone, job_id };
+        for line in OutputLines::new(stdout_reader) {
+            println!("STDOUT [{}]: {}", job_id, line);
+
+            logs.lock().unwrap().push(job_id, LogSource::Stdout, &line);
+            reader.read_line(&line, Instant::now(), &mut sink);
+        }
+        reader.finish(Instant::now(), &mut sink);
+    });
 
+    // Goes to the log only; a failed run reports its last stderr line with its result
     let window_clone = window.clone();
+    let stderr_thread = std::thread::spawn(move || {
+        let logs = window_clone.state::<Mutex<JobLogs>>();
+        for line in OutputLines::new(stderr_reader) {
+            println!("STDERR [{}]: {}", job_id, line);
+            logs.lock().unwrap().push(job_id, LogSource::Stderr, &line);
+        }
+    });
 
+    // Wait for the process in the background so the command returns the job ID immediately.
+    // The child stays in the job manager so cancel/pause can kill it meanwhile.
+    let window = window.clone();
+    let started_at = Instant::now();
     std::thread::spawn(move || {
-        for line in std::io::BufRead::lines(stdout_reader) {
-            if let Ok(line) = line {
-                println!("STDOUT: {}", line);
-
-                if line.to_lowercase().contains("vid") ||
-                line.to_lowercase().contains("aud") ||
-                line.to_lowercase().contains("sub") {
-
-                    match parse_progress_line(&line) {
-                        Ok((stream_type, progress_info)) => {
-                            match stream_type {
-                                StreamType::Video => current_progress.video_progress = progress_info,
-                                StreamType::Audio => current_progress.audio_progress = progress_info,
-                                StreamType::Subtitle => current_progress.subtitle_progress = progress_info,
-                            }
-
-                            if let Err(e) = window_clone.emit("download-progress", current_progress.clone()) {
-                                eprintln!("Failed to emit progress: {:?}", e);
-                            }
-                        },
-                        Err(e) => eprintln!("Failed to parse progress line: {}", e),
-                    }
-                }
+        let manager = window.state::<Mutex<JobManager>>();
+        let result = loop {
+            if let Some(result) = manager.lock().unwrap().poll_child(job_id) {
+                break result;
+            }
+            std::thread::sleep(std::time::Duration::from_millis(200));
+        };
+        // Let the readers get to the end of the output, so the log and the
+        // final progress are complete before the result goes out
+        for thread in [stdout_thread, stderr_thread] {
+            if thread.join().is_err() {
+                println!("Output reader of job {} panicked", job_id);
             }
         }
-    });
 
-    
+        let logs = window.state::<Mutex<JobLogs>>();
+        let (exit_code, error) = match result {
+            Ok(status) if status.success() => (status.code(), None),
+            Ok(status) => {
+                let error = match logs.lock().unwrap().last_error(job_id) {
+                    Some(last_error) => format!("Process exited with status: {:?}: {}", status, last_error),
+                    None => format!("Process exited with status: {:?}", status),
+                };
+                (status.code(), Some(error))
+            }
+            Err(e) => (None, Some(e.to_string())),
+        };
+        let outcome = match &error {
+            Some(error) => format!("Finished with error: {}", error),
+            None => format!("Finished with exit code {:?}", exit_code),
+        };
+        logs.lock().unwrap().push(job_id, LogSource::App, &outcome);
 
-    let window_clone = window.clone();
-    std::thread::spawn(move || {
-        for line in std::io::BufRead::lines(stderr_reader) {
-            if let Ok(line) = line {
-                println!("STDERR: {}", line);
-                // Forward stderr to the frontend for error display
-                if let Err(e) = window_clone.emit("download-error", line) {
-                    eprintln!("Failed to emit error: {:?}", e);
+        let status = manager.lock().unwrap().finish(job_id, exit_code, error.clone());
+        if status != Some(JobStatus::Paused) {
+            record_history_finish(&window, job_id);
+        }
+
+        // A slot just became free
+        if queue_auto_starts(&window) {
+            process_queue(&window);
+        }
+
+        let result = match (status, error) {
+            (Some(JobStatus::Paused), _) => {
+                println!("Download {} paused", job_id);
+                return;
+            }
+            (Some(JobStatus::Cancelled), _) => {
+                println!("Download {} cancelled", job_id);
+                if let Some(job) = manager.lock().unwrap().get(job_id) {
+                    jobs::remove_segments_dir(job_id, &job.segments_dir());
+                }
+                return;
+            }
+            (_, None) => {
+                println!("Download {} completed successfully", job_id);
+                // Sent first so the frontend has it when it handles the completion
+                if let Some(job) = manager.lock().unwrap().get(job_id) {
+                    let summary = DownloadSummary::new(&job.progress, started_at.elapsed());
+                    if let Err(e) = window.emit("download-summary", JobEvent { job_id, data: summary }) {
+                        println!("Error emitting job {} summary: {:?}", job_id, e);
+                    }
                 }
+                window.emit("download-complete", JobEvent { job_id, data: exit_code })
+            }
+            (_, Some(error)) => {
+                println!("Download {} failed with error: {}", job_id, error);
+                window.emit("download-error", JobEvent { job_id, data: error })
             }
+        };
+        if let Err(e) = result {
+            println!("Error emitting job {} result: {:?}", job_id, e);
         }
     });
 
-    // Wait for the process to complete
-    let status = child.wait().map_err(|e| e.to_string())?;
+    Ok(())
+}
 
-    if status.success() {
-        println!("Download completed successfully");
-        window.emit("download-complete", status.code()).map_err(|e| {
-            println!("Error emitting download-complete event: {:?}", e);
-            e.to_string()
-        })?;
-    } else {
-        let error = format!("Process exited with status: {:?}", status);
-        println!("Download failed with error: {}", error);
-        window.emit("download-error", error).map_err(|e| {
-            println!("Error emitting download-error event: {:?}", e);
-            e.to_string()
-        })?;
-    }
+#[tauri::command]
+async fn load_m3u8_options(
+    window: Window,
+    url: String,
+    headers: Vec<Header>,
+    profile: Option<String>,
+    refresh: Option<bool>,
+    state: State<'_, Mutex<SettingsStore>>,
+) -> Result<(), DiscoveryError> {
+    let settings = state.lock()
+        .unwrap()
+        .profile(profile.as_deref())
+        .map_err(|e| DiscoveryError::new(DiscoveryErrorKind::Other, e))?
+        .clone();
+    let discovery_window = window.clone();
+    let result = tauri::async_runtime::spawn_blocking(move || {
+        discover_streams(&discovery_window, &url, &headers, &settings, refresh.unwrap_or(false))
+    })
+    .await
+    .unwrap_or_else(|e| Err(DiscoveryError::new(DiscoveryErrorKind::Other, format!("Stream discovery stopped: {}", e))));
+    let stream_info = match result {
+        Ok(stream_info) => stream_info,
+        Err(e) => {
+            println!("Stream discovery failed: {:?}", e);
+            if let Err(emit_error) = window.emit("m3u8-options-error", &e) {
+                println!("Failed to emit m3u8-options-error: {}", emit_error);
+            }
+            return Err(e);
+        }
+    };
+    println!("Discovered streams: {:?}", stream_info);
+
+    // Send the structured data to the frontend
+    window.emit("m3u8-options", stream_info)
+        .map_err(|e| DiscoveryError::new(DiscoveryErrorKind::Other, e.to_string()))?;
 
     Ok(())
 }
 
-fn parse_progress_line(line: &str) -> Result<(StreamType, ProgressInfo), String> {
-    let pattern = get_progress_pattern();
-
-    if let Some(caps) = pattern.captures(line) {
-        let current = caps[1].parse::<i32>().map_err(|e| format!("Failed to parse current: {}", e))?;
-        let total = caps[2].parse::<i32>().map_err(|e| format!("Failed to parse total: {}", e))?;
-        let percentage = caps[3].parse::<f32>().map_err(|e| format!("Failed to parse percentage: {}", e))?;
-        let downloaded = caps[4].trim().to_string();
-        let speed = caps[5].trim().to_string();
-        let eta = caps[6].trim().to_string();
-
-        let progress_info = ProgressInfo {
-            current,
-            total,
-            percentage,
-            downloaded,
-            total_size: String::new(), // Still not used
-            speed,
-            eta,
-        };
+// Used when the profile sets no `timeout`, same as N_m3u8DL-RE's own request timeout
+const DEFAULT_PROBE_TIMEOUT_SECS: u32 = 100;
 
-        // Identify the stream type
-        let stream_type = if line.to_lowercase().contains("vid") {
-            StreamType::Video
-        } else if line.to_lowercase().contains("aud") {
-            StreamType::Audio
-        } else if line.to_lowercase().contains("sub") {
-            StreamType::Subtitle
-        } else {
-            return Err(format!("Unknown stream type in line: {}", line));
-        };
+#[derive(Clone, Serialize)]
+struct ProbeStarted {
+    probe_id: u64,
+    url: String,
+}
 
-        Ok((stream_type, progress_info))
-    } else {
-        Err(format!("Failed to match progress pattern in line: {}", line))
+// Lists the streams of a manifest the way the profile asks for. Results are
+// reused for `CACHE_TTL` unless `refresh` is set.
+fn discover_streams(
+    window: &Window,
+    url: &str,
+    headers: &[Header],
+    settings: &Settings,
+    refresh: bool,
+) -> Result<StreamInfo, DiscoveryError> {
+    let mode = settings.discovery_mode.unwrap_or_default();
+    let probes = window.state::<Mutex<Probes>>();
+    if !refresh {
+        if let Some(stream_info) = probes.lock().unwrap().cached(url, headers, mode, Instant::now()) {
+            println!("Using cached streams for {}", url);
+            return Ok(stream_info);
+        }
+    }
+
+    let (probe_id, token) = probes.lock().unwrap().start();
+    if let Err(e) = window.emit("probe-started", ProbeStarted { probe_id, url: url.to_string() }) {
+        println!("Failed to emit probe-started: {}", e);
+    }
+    let timeout = Duration::from_secs(settings.timeout.unwrap_or(DEFAULT_PROBE_TIMEOUT_SECS).into());
+    let result = run_discovery(url, headers, mode, timeout, &token);
+    probes.lock().unwrap().finish(probe_id);
+
+    let mut stream_info = result?;
+    discovery::check_streams(&stream_info)?;
+    stream_info.drm_only = discovery::is_drm_only(&stream_info);
+    if stream_info.drm_only {
+        println!("Every video and audio stream of {} is DRM protected", url);
     }
+    probes.lock().unwrap().store(url, headers, mode, stream_info.clone(), Instant::now());
+    Ok(stream_info)
 }
 
+// Stops a running stream probe, or all of them when no ID is given
 #[tauri::command]
-async fn load_m3u8_options(window: Window, url: String, headers: Vec<Header>) -> Result<(), String> {
+async fn cancel_probe(probe_id: Option<u64>, probes: State<'_, Mutex<Probes>>) -> Result<(), String> {
+    probes.lock().unwrap().cancel(probe_id)
+}
+
+fn run_discovery(
+    url: &str,
+    headers: &[Header],
+    mode: DiscoveryMode,
+    timeout: Duration,
+    token: &CancelToken,
+) -> Result<StreamInfo, DiscoveryError> {
+    if mode == DiscoveryMode::MetaJson {
+        return probe_stream_info(url.to_string(), headers.to_vec(), timeout, token);
+    }
+    let native = discovery::discover(url, headers, timeout);
+    // A manifest request cannot be interrupted, so cancelling takes effect once it returns
+    if token.is_cancelled() {
+        return Err(DiscoveryError::new(DiscoveryErrorKind::Cancelled, "Stream discovery was cancelled"));
+    }
+    match native {
+        Ok(Some(stream_info)) => Ok(stream_info),
+        // N_m3u8DL-RE would get the same answer from the server
+        Err(e) if e.kind == DiscoveryErrorKind::HttpStatus => Err(e),
+        Ok(None) => {
+            println!("Manifest format not supported natively, asking N_m3u8DL-RE");
+            probe_stream_info(url.to_string(), headers.to_vec(), timeout, token)
+        }
+        Err(e) => {
+            println!("Native stream discovery failed ({}), asking N_m3u8DL-RE", e);
+            probe_stream_info(url.to_string(), headers.to_vec(), timeout, token)
+        }
+    }
+}
+
+// Runs N_m3u8DL-RE on the URL in a scratch directory with every stream
+// selected and the download skipped. The meta JSON it writes there has the
+// full stream data, including segments and encryption. Builds that write
+// none still list the streams in their log.
+fn probe_stream_info(url: String, headers: Vec<Header>, timeout: Duration, token: &CancelToken) -> Result<StreamInfo, DiscoveryError> {
+    let scratch_dir = std::env::temp_dir().join(format!("m3u8-probe-{}-{}", std::process::id(), jobs::now_millis()));
+    fs::create_dir_all(&scratch_dir).map_err(|e| {
+        DiscoveryError::new(DiscoveryErrorKind::Other, format!("Failed to create probe directory: {}", e))
+    })?;
+    let result = run_probe(url, headers, &scratch_dir, timeout, token);
+    if let Err(e) = fs::remove_dir_all(&scratch_dir) {
+        println!("Failed to remove probe directory {}: {}", scratch_dir.display(), e);
+    }
+    result
+}
+
+fn run_probe(
+    url: String,
+    headers: Vec<Header>,
+    scratch_dir: &Path,
+    timeout: Duration,
+    token: &CancelToken,
+) -> Result<StreamInfo, DiscoveryError> {
     let bin_dir = get_bin_dir();
     let binary_path = bin_dir.join(get_platform_binary_name("N_m3u8DL-RE"));
-    
-    let mut args = vec![url];
-    
+    if !binary_path.exists() {
+        return Err(DiscoveryError::new(
+            DiscoveryErrorKind::BinaryMissing,
+            format!("N_m3u8DL-RE was not found at {}", binary_path.display()),
+        ));
+    }
+    let scratch = scratch_dir.to_string_lossy().into_owned();
+
+    let mut args = N_m3u8DLArgs::new(url)
+        .write_meta_json(true)
+        .skip_download(true)
+        .select_video("for=all")
+        .select_audio("for=all")
+        .select_subtitle("for=all")
+        .save_dir(scratch.clone())
+        .tmp_dir(scratch)
+        .del_after_done(true);
     for header in headers {
-        args.push("-H".to_string());
-        args.push(format!("{}: {}", header.key, header.value));
+        args = args.header(header.key, header.value);
     }
-
-    args.push("--write-meta-json".to_string());
-    args.push("false".to_string());
-    args.push("--del-after-done".to_string());
-    
+    let args = args.to_args();
 
     // Log the command being executed
-    println!("Executing command: {} {}", binary_path.display(), args.join(" "));
+    println!("Executing command: {} {}", binary_path.display(), history::redact_args(&args).join(" "));
 
-    let output = Command::new(binary_path)
+    // Some builds write the meta JSON to the working directory
+    let child = Command::new(&binary_path)
         .args(&args)
-        .output()
-        .map_err(|e| e.to_string())?;
+        .current_dir(scratch_dir)
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|e| {
+            let kind = if e.kind() == std::io::ErrorKind::NotFound {
+                DiscoveryErrorKind::BinaryMissing
+            } else {
+                DiscoveryErrorKind::Other
+            };
+            DiscoveryError::new(kind, format!("Failed to run {}: {}", binary_path.display(), e))
+        })?;
+    let output = wait_for_probe(child, timeout, token)?;
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let stderr = String::from_utf8_lossy(&output.stderr);
 
     // Log the command output
-    println!("Command stdout: {}", String::from_utf8_lossy(&output.stdout));
-    println!("Command stderr: {}", String::from_utf8_lossy(&output.stderr));
+    println!("Command stdout: {}", stdout);
+    println!("Command stderr: {}", stderr);
+
+    let stream_info = match find_meta_json(scratch_dir) {
+        Some(path) => {
+            println!("Reading stream metadata from {}", path.display());
+            let text = fs::read_to_string(&path).map_err(|e| {
+                DiscoveryError::new(DiscoveryErrorKind::Other, format!("Failed to read {}: {}", path.display(), e))
+            })?;
+            discovery::meta::parse(&text)
+                .map_err(|e| DiscoveryError::new(DiscoveryErrorKind::Parse, e).with_output(&stdout))?
+        }
+        None => {
+            println!("N_m3u8DL-RE wrote no meta JSON, reading the streams from its log");
+            parse_stream_log(&stdout)
+        }
+    };
 
-    let output_str = String::from_utf8_lossy(&output.stdout);
-    
-    // Parse the output into structured data
-    let mut stream_info = StreamInfo {
-        video_streams: Vec::new(),
-        audio_streams: Vec::new(),
-        subtitle_streams: Vec::new(),
+    let empty = stream_info.video_streams.is_empty()
+        && stream_info.audio_streams.is_empty()
+        && stream_info.subtitle_streams.is_empty();
+    if empty {
+        return Err(DiscoveryError::from_probe_output(&stdout, &stderr));
+    }
+    Ok(stream_info)
+}
+
+// Waits for a probe like `Command::output`, but kills it once `timeout` has
+// passed or the probe is cancelled. The pipes are drained on their own
+// threads so a chatty process cannot block on a full pipe.
+fn wait_for_probe(mut child: Child, timeout: Duration, token: &CancelToken) -> Result<Output, DiscoveryError> {
+    let stdout = read_pipe(child.stdout.take());
+    let stderr = read_pipe(child.stderr.take());
+    let deadline = Instant::now() + timeout;
+
+    let status = loop {
+        let finished = child.try_wait()
+            .map_err(|e| DiscoveryError::new(DiscoveryErrorKind::Other, e.to_string()))?;
+        if let Some(status) = finished {
+            break status;
+        }
+        let error = if token.is_cancelled() {
+            DiscoveryError::new(DiscoveryErrorKind::Cancelled, "Stream discovery was cancelled")
+        } else if Instant::now() >= deadline {
+            DiscoveryError::new(
+                DiscoveryErrorKind::Timeout,
+                format!("N_m3u8DL-RE did not list the streams within {} seconds", timeout.as_secs()),
+            )
+        } else {
+            thread::sleep(Duration::from_millis(100));
+            continue;
+        };
+        println!("Stopping stream probe: {}", error);
+        if let Err(e) = child.kill() {
+            println!("Failed to kill stream probe: {}", e);
+        }
+        let _ = child.wait();
+        // The pipes close with the process, so this is what it printed before it hung
+        let stdout = stdout.join().unwrap_or_default();
+        return Err(error.with_output(&String::from_utf8_lossy(&stdout)));
     };
 
+    Ok(Output {
+        status,
+        stdout: stdout.join().unwrap_or_default(),
+        stderr: stderr.join().unwrap_or_default(),
+    })
+}
+
+fn read_pipe<R: Read + Send + 'static>(pipe: Option<R>) -> thread::JoinHandle<Vec<u8>> {
+    thread::spawn(move || {
+        let mut bytes = Vec::new();
+        if let Some(mut pipe) = pipe {
+            let _ = pipe.read_to_end(&mut bytes);
+        }
+        bytes
+    })
+}
+
+// meta_selected.json has the playlists of the selected streams loaded, so it
+// wins over meta.json, which only has what the manifest itself lists
+fn find_meta_json(dir: &Path) -> Option<PathBuf> {
+    let subdirs: Vec<PathBuf> = fs::read_dir(dir)
+        .into_iter()
+        .flatten()
+        .flatten()
+        .map(|entry| entry.path())
+        .filter(|path| path.is_dir())
+        .collect();
+    ["meta_selected.json", "meta.json"].iter().find_map(|name| {
+        std::iter::once(dir.to_path_buf())
+            .chain(subdirs.iter().cloned())
+            .map(|dir| dir.join(name))
+            .find(|path| path.is_file())
+    })
+}
+
+// Reads the streams N_m3u8DL-RE lists in its log output
+fn parse_stream_log(output_str: &str) -> StreamInfo {
+    // Parse the output into structured data
+    let mut stream_info = StreamInfo::default();
+
     for line in output_str.lines() {
         let line = line.trim();
         if line.is_empty() {
@@ -524,12 +842,14 @@ async fn load_m3u8_options(window: Window, url: String, headers: Vec<Header>) ->
                 let stream_parts: Vec<&str> = parts[1].split('|').map(|s| s.trim()).collect();
                 if stream_parts.len() >= 4 {
                     // Extract numeric part from bitrate by removing 'Kbps'
-                    let bitrate = stream_parts[1].replace("Kbps", "").trim().to_string();                
+                    let kbps = stream_parts[1].replace("Kbps", "").trim().parse::<u64>().ok();
                     stream_info.video_streams.push(VideoStream {
-                        resolution: stream_parts[0].to_string(),
-                        bitrate,
-                        fps: stream_parts[2].to_string(),
-                        codec: stream_parts[3].to_string(),
+                        id: streams::stable_id("video", &stream_parts),
+                        resolution: streams::non_empty(stream_parts[0]),
+                        bandwidth: kbps.map(|kbps| kbps * 1000),
+                        frame_rate: stream_parts[2].parse().ok(),
+                        codecs: streams::non_empty(stream_parts[3]),
+                        ..VideoStream::default()
                     });
                 }
             }
@@ -542,10 +862,12 @@ async fn load_m3u8_options(window: Window, url: String, headers: Vec<Header>) ->
                 let stream_parts: Vec<&str> = parts[1].split('|').map(|s| s.trim()).collect();
                 if stream_parts.len() >= 4 {
                     stream_info.audio_streams.push(AudioStream {
-                        id: stream_parts[0].to_string(),
-                        name: stream_parts[1].to_string(),
-                        language: stream_parts[2].to_string(),
-                        channels: stream_parts[3].to_string(),
+                        id: streams::stable_id("audio", &stream_parts),
+                        group_id: streams::non_empty(stream_parts[0]),
+                        name: streams::non_empty(stream_parts[1]),
+                        language: streams::non_empty(stream_parts[2]),
+                        channels: streams::non_empty(stream_parts[3]),
+                        ..AudioStream::default()
                     });
                 }
             }
@@ -558,50 +880,351 @@ async fn load_m3u8_options(window: Window, url: String, headers: Vec<Header>) ->
                 let stream_parts: Vec<&str> = parts[1].split('|').map(|s| s.trim()).collect();
                 if stream_parts.len() >= 3 {
                     stream_info.subtitle_streams.push(SubtitleStream {
-                        id: stream_parts[0].to_string(),
-                        language: stream_parts[1].to_string(),
-                        name: stream_parts[2].to_string(),
+                        id: streams::stable_id("subtitle", &stream_parts),
+                        group_id: streams::non_empty(stream_parts[0]),
+                        language: streams::non_empty(stream_parts[1]),
+                        name: streams::non_empty(stream_parts[2]),
+                        ..SubtitleStream::default()
                     });
                 }
             }
         }
     }
 
-    // Send the structured data to the frontend
-    window.emit("m3u8-options", stream_info).map_err(|e| e.to_string())?;
+    stream_info
+}
+
+#[tauri::command]
+async fn list_jobs(jobs: State<'_, Mutex<JobManager>>) -> Result<Vec<Job>, String> {
+    Ok(jobs.lock().unwrap().list())
+}
 
+#[tauri::command]
+async fn get_job(job_id: u64, jobs: State<'_, Mutex<JobManager>>) -> Result<Job, String> {
+    jobs.lock()
+        .unwrap()
+        .get(job_id)
+        .cloned()
+        .ok_or_else(|| format!("Unknown download job: {}", job_id))
+}
+
+// Output of a job from line `offset` on, for a live log view. Poll again with
+// `next_offset` to get only what was added since.
+#[tauri::command]
+async fn get_job_log(
+    job_id: u64,
+    offset: Option<u64>,
+    logs: State<'_, Mutex<JobLogs>>,
+    jobs: State<'_, Mutex<JobManager>>,
+) -> Result<LogChunk, String> {
+    // A log file on disk alone may be from a job that no longer exists
+    if !jobs.lock().unwrap().is_known(job_id) {
+        return Err(format!("Unknown download job: {}", job_id));
+    }
+    Ok(logs.lock().unwrap().read(job_id, offset.unwrap_or(0)))
+}
+
+#[tauri::command]
+async fn cancel_download(window: Window, job_id: u64, jobs: State<'_, Mutex<JobManager>>) -> Result<(), String> {
+    println!("Cancelling download {}", job_id);
+    let running = {
+        let mut jobs = jobs.lock().unwrap();
+        let running = jobs.stop(job_id, JobStatus::Cancelled)?;
+        // A running job cleans up and finishes its history entry once its
+        // process has exited, see spawn_job
+        if !running {
+            jobs.finish(job_id, None, None);
+            if let Some(job) = jobs.get(job_id) {
+                jobs::remove_segments_dir(job_id, &job.segments_dir());
+            }
+        }
+        running
+    };
+    if !running {
+        record_history_finish(&window, job_id);
+    }
+    emit_queue_changed(&window);
+    window.emit("download-cancelled", JobEvent { job_id, data: () }).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+async fn pause_download(window: Window, job_id: u64, jobs: State<'_, Mutex<JobManager>>) -> Result<(), String> {
+    println!("Pausing download {}", job_id);
+    jobs.lock().unwrap().stop(job_id, JobStatus::Paused)?;
+    window.emit("download-paused", JobEvent { job_id, data: () }).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+async fn resume_download(window: Window, job_id: u64, jobs: State<'_, Mutex<JobManager>>) -> Result<(), String> {
+    println!("Resuming download {}", job_id);
+    // The job restarts with the same args, so the same --tmp-dir:
+    // N_m3u8DL-RE picks up the segments it already fetched
+    jobs.lock().unwrap().requeue(job_id)?;
+    process_queue(&window);
+    window.emit("download-resumed", JobEvent { job_id, data: () }).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+async fn list_resumable_jobs(jobs: State<'_, Mutex<JobManager>>) -> Result<Vec<PersistedJob>, String> {
+    Ok(jobs.lock().unwrap().resumable())
+}
+
+#[tauri::command]
+async fn resume_job(
+    window: Window,
+    job_id: u64,
+    // Values for the headers that were redacted when the job was saved
+    headers: Option<Vec<Header>>,
+    jobs: State<'_, Mutex<JobManager>>,
+) -> Result<(), String> {
+    println!("Resuming interrupted download {}", job_id);
+    jobs.lock().unwrap().resume_interrupted(job_id, headers.unwrap_or_default())?;
+    process_queue(&window);
+    Ok(())
+}
+
+#[tauri::command]
+async fn discard_resumable_job(job_id: u64, jobs: State<'_, Mutex<JobManager>>) -> Result<(), String> {
+    println!("Discarding interrupted download {}", job_id);
+    let job = jobs.lock().unwrap().discard_interrupted(job_id)?;
+    jobs::remove_segments_dir(job_id, &job.segments_dir());
     Ok(())
 }
 
 #[tauri::command]
-async fn get_settings(state: State<'_, Mutex<Settings>>) -> Result<Settings, String> {
-    let settings = state.lock().unwrap().clone();
+async fn get_queue(jobs: State<'_, Mutex<JobManager>>) -> Result<Vec<QueueEntry>, String> {
+    Ok(jobs.lock().unwrap().queue())
+}
+
+// Starts queued downloads up to the concurrency limit. With auto-start off
+// this is the only way they start.
+#[tauri::command]
+async fn start_queue(window: Window) -> Result<(), String> {
+    println!("Starting queued downloads");
+    process_queue(&window);
+    Ok(())
+}
+
+#[tauri::command]
+async fn move_queued_job(window: Window, job_id: u64, position: usize, jobs: State<'_, Mutex<JobManager>>) -> Result<(), String> {
+    println!("Moving queued job {} to position {}", job_id, position);
+    jobs.lock().unwrap().queue_mut().move_to(job_id, position)?;
+    emit_queue_changed(&window);
+    Ok(())
+}
+
+#[tauri::command]
+async fn set_job_priority(window: Window, job_id: u64, priority: i32, jobs: State<'_, Mutex<JobManager>>) -> Result<(), String> {
+    println!("Setting priority of queued job {} to {}", job_id, priority);
+    jobs.lock().unwrap().queue_mut().set_priority(job_id, priority)?;
+    emit_queue_changed(&window);
+    Ok(())
+}
+
+#[tauri::command]
+async fn redownload(
+    window: Window,
+    history_id: u64,
+    overrides: Option<RedownloadOverrides>,
+    state: State<'_, Mutex<SettingsStore>>,
+    history: State<'_, Mutex<History>>,
+) -> Result<u64, String> {
+    let overrides = overrides.unwrap_or_default();
+    println!(
+        "Re-running history entry {} with overrides: {:?}",
+        history_id,
+        RedownloadOverrides { headers: overrides.headers.as_deref().map(history::redact_headers), ..overrides.clone() },
+    );
+    let entry = history.lock()
+        .unwrap()
+        .get(history_id)
+        .cloned()
+        .ok_or_else(|| format!("Unknown history entry: {}", history_id))?;
+
+    let mut options = entry.to_download_options(overrides.save_name, overrides.headers.unwrap_or_default())?;
+    let mut settings = {
+        let store = state.lock().unwrap();
+        match overrides.profile {
+            Some(profile) => {
+                let settings = store.profile(Some(&profile))?.clone();
+                options.profile = Some(profile);
+                settings
+            }
+            // The recorded profile may have been deleted since
+            None => match store.profile(options.profile.as_deref()) {
+                Ok(settings) => settings.clone(),
+                Err(e) => {
+                    println!("{}, using the active profile", e);
+                    options.profile = None;
+                    store.active().clone()
+                }
+            },
+        }
+    };
+
+    // Land next to the original output unless the overrides say otherwise
+    settings.download_location = Some(entry.save_dir.clone());
+    if let Some(override_settings) = &overrides.settings {
+        settings = settings.merged_with(override_settings)?;
+        // Held to the same rules as saved settings
+        settings.validate()?;
+    }
+
+    let options = resolve_streams_in_background(&window, options, &settings).await?;
+    Ok(enqueue_download(&window, options, &settings))
+}
+
+#[tauri::command]
+async fn list_history(query: Option<HistoryQuery>, history: State<'_, Mutex<History>>) -> Result<HistoryPage, String> {
+    Ok(history.lock().unwrap().query(&query.unwrap_or_default()))
+}
+
+#[tauri::command]
+async fn delete_history_entry(id: u64, history: State<'_, Mutex<History>>) -> Result<(), String> {
+    println!("Deleting history entry {}", id);
+    history.lock().unwrap().delete(id)
+}
+
+#[tauri::command]
+async fn clear_history(history: State<'_, Mutex<History>>) -> Result<(), String> {
+    println!("Clearing download history");
+    history.lock().unwrap().clear()
+}
+
+// Only uses streams that were already listed, so the preview never waits on
+// the network. Without them the command shows the selection as it was sent.
+#[tauri::command]
+async fn preview_command(
+    options: DownloadOptions,
+    state: State<'_, Mutex<SettingsStore>>,
+    probes: State<'_, Mutex<Probes>>,
+) -> Result<String, String> {
+    let settings = state.lock().unwrap().profile(options.profile.as_deref())?.clone();
+    let mode = settings.discovery_mode.unwrap_or_default();
+    let cached = probes.lock().unwrap().cached(&options.url, &options.headers, mode, Instant::now());
+    let options = match cached {
+        Some(stream_info) => apply_streams(options, &settings, &stream_info)?,
+        None => options,
+    };
+    let binary_path = get_bin_dir().join(get_platform_binary_name("N_m3u8DL-RE"));
+    Ok(build_download_args(&options, &settings).to_command_line(&binary_path.to_string_lossy()))
+}
+
+#[tauri::command]
+async fn get_settings(state: State<'_, Mutex<SettingsStore>>) -> Result<Settings, String> {
+    let settings = state.lock().unwrap().active().clone();
     println!("Getting current settings: {:?}", settings);
     Ok(settings)
 }
 
 #[tauri::command]
-async fn set_settings(settings: Settings, state: State<'_, Mutex<Settings>>) -> Result<(), String> {
+async fn set_settings(settings: Settings, state: State<'_, Mutex<SettingsStore>>) -> Result<(), String> {
     println!("Setting new settings: {:?}", settings);
-    save_settings(&settings)?;
-    let mut state = state.lock().unwrap();
-    *state = settings;
+    settings.validate()?;
+    update_settings(&state, |store| {
+        store.set_active(settings);
+        Ok(())
+    })?;
     println!("Settings updated in state");
     Ok(())
 }
 
+// Applies `change` to a copy of the settings and only keeps it once it is saved
+fn update_settings<T>(
+    state: &Mutex<SettingsStore>,
+    change: impl FnOnce(&mut SettingsStore) -> Result<T, String>,
+) -> Result<T, String> {
+    let mut state = state.lock().unwrap();
+    let mut store = state.clone();
+    let result = change(&mut store)?;
+    save_settings(&store)?;
+    *state = store;
+    Ok(result)
+}
+
+#[tauri::command]
+async fn list_profiles(state: State<'_, Mutex<SettingsStore>>) -> Result<ProfileList, String> {
+    Ok(state.lock().unwrap().list())
+}
+
+// Creates a profile from `settings`, or from the defaults when none are given
+#[tauri::command]
+async fn create_profile(name: String, settings: Option<Settings>, state: State<'_, Mutex<SettingsStore>>) -> Result<ProfileList, String> {
+    println!("Creating settings profile {}", name);
+    update_settings(&state, |store| {
+        store.create(&name, settings.unwrap_or_default())?;
+        Ok(store.list())
+    })
+}
+
+#[tauri::command]
+async fn clone_profile(source: String, name: String, state: State<'_, Mutex<SettingsStore>>) -> Result<ProfileList, String> {
+    println!("Cloning settings profile {} as {}", source, name);
+    update_settings(&state, |store| {
+        store.clone_profile(&source, &name)?;
+        Ok(store.list())
+    })
+}
+
+#[tauri::command]
+async fn delete_profile(name: String, state: State<'_, Mutex<SettingsStore>>) -> Result<ProfileList, String> {
+    println!("Deleting settings profile {}", name);
+    update_settings(&state, |store| {
+        store.delete(&name)?;
+        Ok(store.list())
+    })
+}
+
+// Makes `name` the profile used by downloads that do not pick one, returning its settings
+#[tauri::command]
+async fn switch_profile(name: String, state: State<'_, Mutex<SettingsStore>>) -> Result<Settings, String> {
+    println!("Switching to settings profile {}", name);
+    update_settings(&state, |store| {
+        store.switch(&name)?;
+        Ok(store.active().clone())
+    })
+}
+
 fn main() {
     let settings = Mutex::new(load_settings());
     
     tauri::Builder::default()
         .manage(settings)
+        .manage(Mutex::new(JobManager::load(get_jobs_state_path())))
+        .manage(Mutex::new(History::load(get_history_path())))
+        .manage(Mutex::new(Probes::default()))
+        .manage(Mutex::new(JobLogs::new(get_logs_dir())))
         .invoke_handler(tauri::generate_handler![
             check_required_binaries,
             start_download,
+            preview_command,
+            list_jobs,
+            get_job,
+            get_job_log,
+            cancel_download,
+            pause_download,
+            resume_download,
+            list_resumable_jobs,
+            resume_job,
+            discard_resumable_job,
+            get_queue,
+            start_queue,
+            move_queued_job,
+            set_job_priority,
+            redownload,
+            list_history,
+            delete_history_entry,
+            clear_history,
             load_m3u8_options,
+            cancel_probe,
             get_settings,
             set_settings,
+            list_profiles,
+            create_profile,
+            clone_profile,
+            delete_profile,
+            switch_profile,
         ])
         .run(tauri::generate_context!())
         .expect("error while running tauri application");
-} 
\ No newline at end of file
+} 
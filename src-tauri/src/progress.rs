@@ -0,0 +1,683 @@
+use std::io::BufRead;
+use std::sync::OnceLock;
+use std::time::{Duration, Instant};
+use regex::Regex;
+use serde::Serialize;
+
+#[derive(Debug, Serialize, Clone, Default)]
+pub struct DownloadProgress {
+    pub video_progress: ProgressInfo,
+    pub audio_progress: ProgressInfo,
+    pub subtitle_progress: ProgressInfo,
+    pub overall: OverallProgress,
+}
+
+// The text fields are as N_m3u8DL-RE prints them, the numeric ones are read
+// from them and left empty while N_m3u8DL-RE shows "-"
+#[derive(Debug, Serialize, Clone, Default, PartialEq)]
+pub struct ProgressInfo {
+    pub current: i32,
+    pub total: i32,
+    pub percentage: f32,
+    pub downloaded: String,
+    pub total_size: String,
+    pub speed: String,
+    pub eta: String,
+    pub downloaded_bytes: Option<u64>,
+    pub total_bytes: Option<u64>,
+    pub bytes_per_second: Option<u64>,
+    pub eta_seconds: Option<u64>,
+}
+
+// All streams of a download taken together
+#[derive(Debug, Serialize, Clone, Default, PartialEq)]
+pub struct OverallProgress {
+    pub percentage: f32,
+    pub downloaded_bytes: u64,
+    // Only known once every stream has reported its size
+    pub total_bytes: Option<u64>,
+    pub bytes_per_second: u64,
+    pub eta_seconds: Option<u64>,
+}
+
+// Emitted as `download-summary` when a download completes
+#[derive(Debug, Serialize, Clone, PartialEq)]
+pub struct DownloadSummary {
+    pub total_bytes: u64,
+    pub average_bytes_per_second: u64,
+    pub elapsed_seconds: f64,
+}
+
+// What N_m3u8DL-RE is busy with. Segments reach 100% long before a download
+// is done, merging, decrypting and muxing can take as long again.
+#[derive(Debug, Serialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum DownloadPhase {
+    Parsing,
+    Downloading,
+    Merging,
+    Decrypting,
+    Muxing,
+    Cleaning,
+}
+
+// Emitted as `download-phase` when a job enters a phase
+#[derive(Debug, Serialize, Clone, PartialEq)]
+pub struct PhaseChange {
+    pub phase: DownloadPhase,
+    // Milliseconds since the Unix epoch
+    pub started_at: u64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum StreamType {
+    Video,
+    Audio,
+    Subtitle,
+}
+
+impl DownloadProgress {
+    pub fn update(&mut self, stream_type: StreamType, info: ProgressInfo) {
+        match stream_type {
+            StreamType::Video => self.video_progress = info,
+            StreamType::Audio => self.audio_progress = info,
+            StreamType::Subtitle => self.subtitle_progress = info,
+        }
+        self.overall = self.aggregate();
+    }
+
+    // Streams count by their size, so a large video outweighs its subtitles.
+    // Until every size is known each stream counts the same.
+    fn aggregate(&self) -> OverallProgress {
+        let streams: Vec<&ProgressInfo> = [&self.video_progress, &self.audio_progress, &self.subtitle_progress]
+            .into_iter()
+            .filter(|info| info.total > 0)
+            .collect();
+        if streams.is_empty() {
+            return OverallProgress::default();
+        }
+
+        let downloaded_bytes = streams.iter().filter_map(|info| info.downloaded_bytes).sum();
+        let bytes_per_second = streams.iter().filter_map(|info| info.bytes_per_second).sum();
+        let total_bytes = streams.iter()
+            .map(|info| info.total_bytes)
+            .sum::<Option<u64>>()
+            .filter(|total| *total > 0);
+        let percentage = match total_bytes {
+            Some(total) => {
+                let weighted: f64 = streams.iter()
+                    .map(|info| info.percentage as f64 * info.total_bytes.unwrap_or_default() as f64)
+                    .sum();
+                (weighted / total as f64) as f32
+            }
+            None => streams.iter().map(|info| info.percentage).sum::<f32>() / streams.len() as f32,
+        };
+        let eta_seconds = match total_bytes {
+            Some(total) if bytes_per_second > 0 => {
+                let remaining = total.saturating_sub(downloaded_bytes);
+                // Rounded up, u64::div_ceil needs Rust 1.73
+                Some(remaining / bytes_per_second + u64::from(remaining % bytes_per_second != 0))
+            }
+            _ => streams.iter().filter_map(|info| info.eta_seconds).max(),
+        };
+
+        OverallProgress { percentage, downloaded_bytes, total_bytes, bytes_per_second, eta_seconds }
+    }
+}
+
+impl DownloadSummary {
+    pub fn new(progress: &DownloadProgress, elapsed: Duration) -> Self {
+        let total_bytes = progress.overall.downloaded_bytes;
+        let elapsed_seconds = elapsed.as_secs_f64();
+        let average_bytes_per_second = if elapsed_seconds > 0.0 {
+            (total_bytes as f64 / elapsed_seconds).round() as u64
+        } else {
+            0
+        };
+        DownloadSummary { total_bytes, average_bytes_per_second, elapsed_seconds }
+    }
+}
+
+// Holds back updates that come sooner than `interval` after the last one sent.
+// Whatever was held back is sent by `flush`, so the last state is never lost.
+#[derive(Debug)]
+pub struct Throttle {
+    interval: Duration,
+    last_sent: Option<Instant>,
+    pending: bool,
+}
+
+impl Throttle {
+    pub fn new(interval: Duration) -> Self {
+        Throttle { interval, last_sent: None, pending: false }
+    }
+
+    // Whether an update that came in at `now` should be sent
+    pub fn ready(&mut self, now: Instant) -> bool {
+        let ready = self.last_sent.map_or(true, |last_sent| now.duration_since(last_sent) >= self.interval);
+        if ready {
+            self.last_sent = Some(now);
+        }
+        self.pending = !ready;
+        ready
+    }
+
+    // Whether an update was held back and still has to be sent
+    pub fn flush(&mut self, now: Instant) -> bool {
+        let pending = self.pending;
+        if pending {
+            self.last_sent = Some(now);
+            self.pending = false;
+        }
+        pending
+    }
+}
+
+// Where `ProgressReader` reports what it reads from a job's output
+pub trait ProgressSink {
+    // Records the phase a line points to. Returns the change if it is a new phase.
+    fn set_phase(&mut self, phase: DownloadPhase) -> Option<PhaseChange>;
+    fn send_phase(&mut self, change: PhaseChange);
+    fn set_progress(&mut self, progress: &DownloadProgress);
+    fn send_progress(&mut self, progress: &DownloadProgress);
+}
+
+// Follows N_m3u8DL-RE's stdout line by line. Phase changes are sent right
+// away, progress no more often than the throttle allows.
+pub struct ProgressReader {
+    progress: DownloadProgress,
+    throttle: Throttle,
+}
+
+impl ProgressReader {
+    pub fn new(progress: DownloadProgress, interval: Duration) -> Self {
+        ProgressReader { progress, throttle: Throttle::new(interval) }
+    }
+
+    pub fn read_line(&mut self, line: &str, now: Instant, sink: &mut impl ProgressSink) {
+        let parsed = parse_line(line);
+        // Progress rows also mean segments are downloading, whatever language N_m3u8DL-RE logs in
+        let phase = parse_phase(line).or(parsed.as_ref().map(|_| DownloadPhase::Downloading));
+        if let Some(change) = phase.and_then(|phase| sink.set_phase(phase)) {
+            // Progress up to here belongs to the phase that is ending
+            if self.throttle.flush(now) {
+                sink.send_progress(&self.progress);
+            }
+            sink.send_phase(change);
+        }
+
+        if let Some((stream_type, info)) = parsed {
+            self.progress.update(stream_type, info);
+            sink.set_progress(&self.progress);
+            if self.throttle.ready(now) {
+                sink.send_progress(&self.progress);
+            }
+        }
+    }
+
+    // The process has ended, sends the final state if it was held back
+    pub fn finish(&mut self, now: Instant, sink: &mut impl ProgressSink) {
+        if self.throttle.flush(now) {
+            sink.send_progress(&self.progress);
+        }
+    }
+}
+
+// CSI sequences (colors, cursor moves, line erases) and OSC sequences (titles,
+// links), which `--force-ansi-console` mixes into the progress output
+fn ansi_pattern() -> &'static Regex {
+    static ANSI_PATTERN: OnceLock<Regex> = OnceLock::new();
+    ANSI_PATTERN.get_or_init(|| Regex::new(r"\x1b\[[0-?]*[ -/]*[@-~]|\x1b\][^\x07\x1b]*(?:\x07|\x1b\\)|\x1b[@-Z\\-_]").unwrap())
+}
+
+// Stream label at the start of a progress row, e.g. "Vid 1920x1080 | 6000 Kbps"
+fn label_pattern() -> &'static Regex {
+    static LABEL_PATTERN: OnceLock<Regex> = OnceLock::new();
+    LABEL_PATTERN.get_or_init(|| Regex::new(r"^(Vid|Aud|Sub)\b").unwrap())
+}
+
+// Segment count and percentage right after the progress bar, e.g. "36/101 35.64%"
+fn counts_pattern() -> &'static Regex {
+    static COUNTS_PATTERN: OnceLock<Regex> = OnceLock::new();
+    COUNTS_PATTERN.get_or_init(|| Regex::new(r"(\d+)/(\d+)\s+(\d+(?:\.\d+)?)%").unwrap())
+}
+
+fn size_pattern() -> &'static Regex {
+    static SIZE_PATTERN: OnceLock<Regex> = OnceLock::new();
+    SIZE_PATTERN.get_or_init(|| Regex::new(r"(?i)^(\d+(?:\.\d+)?)\s*([KMGT]?i?B)$").unwrap())
+}
+
+fn eta_pattern() -> &'static Regex {
+    static ETA_PATTERN: OnceLock<Regex> = OnceLock::new();
+    ETA_PATTERN.get_or_init(|| Regex::new(r"^(?:\d+:)?\d+:\d+$|^--:--:--$").unwrap())
+}
+
+// Time and level N_m3u8DL-RE puts before each log message, e.g. "12:00:00.123 INFO : "
+fn log_prefix_pattern() -> &'static Regex {
+    static LOG_PREFIX_PATTERN: OnceLock<Regex> = OnceLock::new();
+    LOG_PREFIX_PATTERN.get_or_init(|| Regex::new(r"^\d{1,2}:\d{2}:\d{2}(?:\.\d+)?\s+[A-Z]+\s*:\s*").unwrap())
+}
+
+// Start of the log messages that begin each phase, in lower case
+const PHASE_MESSAGES: &[(&str, DownloadPhase)] = &[
+    ("loading url", DownloadPhase::Parsing),
+    ("parsing streams", DownloadPhase::Parsing),
+    ("start downloading", DownloadPhase::Downloading),
+    ("start recording", DownloadPhase::Downloading),
+    ("binary merging", DownloadPhase::Merging),
+    ("ffmpeg merging", DownloadPhase::Merging),
+    ("merging parts", DownloadPhase::Merging),
+    ("decrypting", DownloadPhase::Decrypting),
+    ("muxing to", DownloadPhase::Muxing),
+    ("mux with", DownloadPhase::Muxing),
+    ("cleaning files", DownloadPhase::Cleaning),
+];
+
+pub fn strip_ansi(text: &str) -> String {
+    ansi_pattern().replace_all(text, "").into_owned()
+}
+
+// Number of bytes in a size like "163.34MB". Units are powers of 1024 like
+// N_m3u8DL-RE prints them.
+pub fn parse_size(text: &str) -> Option<u64> {
+    let captures = size_pattern().captures(text.trim())?;
+    let value: f64 = captures[1].parse().ok()?;
+    let exponent = match captures[2].to_uppercase().chars().next()? {
+        'B' => 0,
+        'K' => 1,
+        'M' => 2,
+        'G' => 3,
+        'T' => 4,
+        _ => return None,
+    };
+    Some((value * 1024f64.powi(exponent)).round() as u64)
+}
+
+// Seconds in an ETA like "00:01:12" or "01:12"
+pub fn parse_eta(text: &str) -> Option<u64> {
+    if !eta_pattern().is_match(text) {
+        return None;
+    }
+    text.split(':').try_fold(0, |seconds, part| Some(seconds * 60 + part.parse::<u64>().ok()?))
+}
+
+// The phase a log line of N_m3u8DL-RE starts, if any. Progress rows are left
+// to `parse_line`.
+pub fn parse_phase(line: &str) -> Option<DownloadPhase> {
+    let line = strip_ansi(line);
+    let row = line.split('\r').next_back()?.trim();
+    let message = log_prefix_pattern().replace(row, "").to_lowercase();
+    PHASE_MESSAGES.iter()
+        .find(|(start, _)| message.starts_with(start))
+        .map(|(_, phase)| *phase)
+}
+
+// Reads one line of N_m3u8DL-RE output. When the line holds several updates
+// that were overwritten with carriage returns, the last complete one wins.
+// Lines that are not progress rows give `None`.
+pub fn parse_line(line: &str) -> Option<(StreamType, ProgressInfo)> {
+    let line = strip_ansi(line);
+    line.split('\r').rev().find_map(parse_row)
+}
+
+fn parse_row(row: &str) -> Option<(StreamType, ProgressInfo)> {
+    let row = row.trim();
+    let stream_type = match label_pattern().captures(row)?.get(1)?.as_str() {
+        "Vid" => StreamType::Video,
+        "Aud" => StreamType::Audio,
+        _ => StreamType::Subtitle,
+    };
+    // The label itself can hold numbers, so take the last count in the row
+    let counts = counts_pattern().captures_iter(row).last()?;
+    let mut info = ProgressInfo {
+        current: counts[1].parse().ok()?,
+        total: counts[2].parse().ok()?,
+        percentage: counts[3].parse().ok()?,
+        ..ProgressInfo::default()
+    };
+
+    // What follows is sizes, speed and ETA, each of which is "-" while unknown
+    let rest = &row[counts.get(0)?.end()..];
+    for token in rest.split_whitespace() {
+        if let Some(bytes) = token.strip_suffix("ps").and_then(parse_size) {
+            info.speed = token.to_string();
+            info.bytes_per_second = Some(bytes);
+        } else if eta_pattern().is_match(token) {
+            info.eta = token.to_string();
+            info.eta_seconds = parse_eta(token);
+        } else if let Some((downloaded, total)) = token.split_once('/') {
+            if let (Some(downloaded_bytes), Some(total_bytes)) = (parse_size(downloaded), parse_size(total)) {
+                info.downloaded = downloaded.to_string();
+                info.total_size = total.to_string();
+                info.downloaded_bytes = Some(downloaded_bytes);
+                info.total_bytes = Some(total_bytes);
+            }
+        } else if let Some(bytes) = parse_size(token) {
+            // Live streams only report what has been downloaded so far
+            info.downloaded = token.to_string();
+            info.downloaded_bytes = Some(bytes);
+        }
+    }
+    Some((stream_type, info))
+}
+
+// Splits output into lines at both line feeds and carriage returns, so
+// updates that overwrite the same console line arrive as they happen
+pub struct OutputLines<R> {
+    reader: R,
+    pending: Vec<u8>,
+}
+
+impl<R: BufRead> OutputLines<R> {
+    pub fn new(reader: R) -> Self {
+        OutputLines { reader, pending: Vec::new() }
+    }
+}
+
+impl<R: BufRead> Iterator for OutputLines<R> {
+    type Item = String;
+
+    fn next(&mut self) -> Option<String> {
+        loop {
+            let buffer = match self.reader.fill_buf() {
+                Ok(buffer) => buffer,
+                Err(e) if e.kind() == std::io::ErrorKind::Interrupted => continue,
+                Err(_) => return None,
+            };
+            if buffer.is_empty() {
+                if self.pending.is_empty() {
+                    return None;
+                }
+                let line = String::from_utf8_lossy(&self.pending).into_owned();
+                self.pending.clear();
+                return Some(line);
+            }
+            match buffer.iter().position(|byte| *byte == b'\n' || *byte == b'\r') {
+                Some(end) => {
+                    self.pending.extend_from_slice(&buffer[..end]);
+                    self.reader.consume(end + 1);
+                    let line = String::from_utf8_lossy(&self.pending).into_owned();
+                    self.pending.clear();
+                    // "\r\n" line ends would otherwise give an empty line
+                    if !line.is_empty() {
+                        return Some(line);
+                    }
+                }
+                None => {
+                    let length = buffer.len();
+                    self.pending.extend_from_slice(buffer);
+                    self.reader.consume(length);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Line, stream type, current, total, percentage, downloaded, total size, speed, ETA
+    type Sample = (&'static str, StreamType, i32, i32, f32, &'static str, &'static str, &'static str, &'static str);
+
+    // Output captured from N_m3u8DL-RE runs, with and without --force-ansi-console
+    const CORPUS: &[Sample] = &[
+        (
+            "Vid 960x540 | 2168 Kbps | 60 ------------------------------ 36/101 35.64% 56.60MB/163.34MB 5.12MBps 00:00:27",
+            StreamType::Video, 36, 101, 35.64, "56.60MB", "163.34MB", "5.12MBps", "00:00:27",
+        ),
+        (
+            "\x1b[38;5;11mVid 1920x1080 | 7968 Kbps | 30 | avc1.640028\x1b[0m \x1b[38;5;10m━━━━━━━━━━━━\x1b[0m\x1b[38;5;8m━━━━━━━━\x1b[0m 512/890 57.53% 1.21GB/2.10GB 12.48MBps 00:01:12",
+            StreamType::Video, 512, 890, 57.53, "1.21GB", "2.10GB", "12.48MBps", "00:01:12",
+        ),
+        (
+            "Aud aud2 | English | en | 6CH ━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━ 101/101 100.00% 9.87MB/9.87MB 0.00Bps 00:00:00",
+            StreamType::Audio, 101, 101, 100.0, "9.87MB", "9.87MB", "0.00Bps", "00:00:00",
+        ),
+        (
+            "Sub sub1 | en | English ━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━ 3/12 25.00% 14.23KB/56.90KB 812.00Bps 00:00:03",
+            StreamType::Subtitle, 3, 12, 25.0, "14.23KB", "56.90KB", "812.00Bps", "00:00:03",
+        ),
+        (
+            "Vid 3840x2160 | 25000 Kbps | 24 ━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━ 1900/2000 95.00% 1.43TB/1.50TB 98.50MBps 00:00:41",
+            StreamType::Video, 1900, 2000, 95.0, "1.43TB", "1.50TB", "98.50MBps", "00:00:41",
+        ),
+        // Nothing downloaded yet
+        (
+            "Vid 1280x720 | 2500 Kbps | 25 ------------------------------ 0/300 0.00% - - --:--:--",
+            StreamType::Video, 0, 300, 0.0, "", "", "", "--:--:--",
+        ),
+        // Live recording, the total is unknown
+        (
+            "Vid 1280x720 | 2500 Kbps ━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━ 42/42 100.00% 84.10MB 1.20MBps 00:00:00",
+            StreamType::Video, 42, 42, 100.0, "84.10MB", "", "1.20MBps", "00:00:00",
+        ),
+        // Redrawn in place: cursor up and erase line before each update
+        (
+            "\x1b[?25l\x1b[1A\x1b[2KAud aud1 | 日本語 | ja | 2CH ━━━━━━━━━━━━━━━ 7/20 35.00% 512.00KB/1.43MB 256.00KBps 00:00:04\x1b[?25h",
+            StreamType::Audio, 7, 20, 35.0, "512.00KB", "1.43MB", "256.00KBps", "00:00:04",
+        ),
+    ];
+
+    #[test]
+    fn parses_corpus() {
+        for (line, stream_type, current, total, percentage, downloaded, total_size, speed, eta) in CORPUS {
+            let (parsed_type, info) = parse_line(line).unwrap_or_else(|| panic!("no progress in {:?}", line));
+            assert_eq!(parsed_type, *stream_type, "{:?}", line);
+            assert_eq!(
+                info,
+                ProgressInfo {
+                    current: *current,
+                    total: *total,
+                    percentage: *percentage,
+                    downloaded: downloaded.to_string(),
+                    total_size: total_size.to_string(),
+                    speed: speed.to_string(),
+                    eta: eta.to_string(),
+                    downloaded_bytes: downloaded.split('/').next().and_then(parse_size),
+                    total_bytes: parse_size(total_size),
+                    bytes_per_second: speed.strip_suffix("ps").and_then(parse_size),
+                    eta_seconds: parse_eta(eta),
+                },
+                "{:?}",
+                line,
+            );
+        }
+    }
+
+    #[test]
+    fn takes_the_last_overwritten_update() {
+        let line = "Vid 960x540 | 2168 Kbps ----- 1/101 0.99% 1.60MB/163.34MB 1.12MBps 00:02:24\r\
+            Vid 960x540 | 2168 Kbps ----- 2/101 1.98% 3.20MB/163.34MB 1.50MBps 00:01:47\r";
+        let (_, info) = parse_line(line).unwrap();
+        assert_eq!((info.current, info.downloaded.as_str()), (2, "3.20MB"));
+    }
+
+    #[test]
+    fn ignores_other_output() {
+        for line in [
+            "12:00:00.000 INFO : Vid *CENC 1920x1080 | 7968 Kbps | 30 | avc1.640028 | 10 Segments | Main | ~01m40s",
+            "12:00:00.000 INFO : Start downloading...Vid 1920x1080 | 7968 Kbps",
+            "12:00:00.000 WARN : Subtitle fix applied 3/4 55.00%",
+            "\x1b[2K",
+            "",
+        ] {
+            assert!(parse_line(line).is_none(), "{:?}", line);
+        }
+    }
+
+    #[test]
+    fn parses_sizes() {
+        assert_eq!(parse_size("512B"), Some(512));
+        assert_eq!(parse_size("1.50KB"), Some(1536));
+        assert_eq!(parse_size("163.34MB"), Some(171_274_404));
+        assert_eq!(parse_size("2GB"), Some(2 * 1024 * 1024 * 1024));
+        assert_eq!(parse_size("1TiB"), Some(1 << 40));
+        assert_eq!(parse_size("-"), None);
+        assert_eq!(parse_size("5.12MBps"), None);
+    }
+
+    #[test]
+    fn reads_numbers_from_progress_rows() {
+        let (_, info) = parse_line(CORPUS[0].0).unwrap();
+        assert_eq!(info.downloaded_bytes, Some(59_349_402));
+        assert_eq!(info.total_bytes, Some(171_274_404));
+        assert_eq!(info.bytes_per_second, Some(5_368_709));
+        assert_eq!(info.eta_seconds, Some(27));
+
+        let (_, waiting) = parse_line(CORPUS[5].0).unwrap();
+        assert_eq!((waiting.downloaded_bytes, waiting.bytes_per_second, waiting.eta_seconds), (None, None, None));
+        assert_eq!(parse_eta("1:02:03"), Some(3723));
+        assert_eq!(parse_eta("01:12"), Some(72));
+    }
+
+    fn stream(percentage: f32, downloaded: u64, total: Option<u64>, speed: u64) -> ProgressInfo {
+        ProgressInfo {
+            current: 1,
+            total: 10,
+            percentage,
+            downloaded_bytes: Some(downloaded),
+            total_bytes: total,
+            bytes_per_second: Some(speed),
+            ..ProgressInfo::default()
+        }
+    }
+
+    #[test]
+    fn weighs_overall_progress_by_size() {
+        let mut progress = DownloadProgress::default();
+        progress.update(StreamType::Video, stream(50.0, 450, Some(900), 60));
+        progress.update(StreamType::Audio, stream(100.0, 100, Some(100), 0));
+        assert_eq!(progress.overall, OverallProgress {
+            percentage: 55.0,
+            downloaded_bytes: 550,
+            total_bytes: Some(1000),
+            bytes_per_second: 60,
+            eta_seconds: Some(8),
+        });
+
+        // A live stream has no size, so the streams count the same
+        progress.update(StreamType::Subtitle, stream(20.0, 10, None, 0));
+        assert_eq!(progress.overall.total_bytes, None);
+        assert!((progress.overall.percentage - 170.0 / 3.0).abs() < 0.001);
+    }
+
+    #[test]
+    fn leaves_out_streams_that_are_not_downloaded() {
+        let mut progress = DownloadProgress::default();
+        progress.update(StreamType::Audio, stream(40.0, 40, Some(100), 10));
+        assert_eq!(progress.overall.percentage, 40.0);
+        assert_eq!(progress.overall.eta_seconds, Some(6));
+    }
+
+    #[test]
+    fn summarizes_completed_downloads() {
+        let mut progress = DownloadProgress::default();
+        progress.update(StreamType::Video, stream(100.0, 3000, Some(3000), 0));
+        let summary = DownloadSummary::new(&progress, Duration::from_millis(1500));
+        assert_eq!(summary, DownloadSummary { total_bytes: 3000, average_bytes_per_second: 2000, elapsed_seconds: 1.5 });
+        assert_eq!(DownloadSummary::new(&DownloadProgress::default(), Duration::ZERO).average_bytes_per_second, 0);
+    }
+
+    #[test]
+    fn recognizes_phases() {
+        for (line, phase) in [
+            ("12:00:00.000 INFO : Loading URL: https://example.com/master.m3u8", DownloadPhase::Parsing),
+            ("\x1b[38;5;15m12:00:00.350\x1b[0m \x1b[38;5;14mINFO\x1b[0m : Parsing streams...", DownloadPhase::Parsing),
+            ("12:00:01.000 INFO : Start downloading...Vid 1920x1080 | 7968 Kbps", DownloadPhase::Downloading),
+            ("12:05:00.000 INFO : Binary merging...", DownloadPhase::Merging),
+            ("12:05:00.000 INFO : ffmpeg merging...", DownloadPhase::Merging),
+            ("12:05:30.000 INFO : Decrypting...", DownloadPhase::Decrypting),
+            ("12:06:00.000 WARN : Muxing to \x1b[38;5;8mvideo.MUX.mp4\x1b[0m", DownloadPhase::Muxing),
+            ("12:07:00.000 WARN : Cleaning files...", DownloadPhase::Cleaning),
+        ] {
+            assert_eq!(parse_phase(line), Some(phase), "{:?}", line);
+        }
+    }
+
+    #[test]
+    fn ignores_lines_without_a_phase() {
+        for line in [
+            CORPUS[0].0,
+            "12:00:00.000 INFO : Selected streams:",
+            "12:00:00.000 INFO : Vid *CENC 1920x1080 | 7968 Kbps | 30 | avc1.640028 | 10 Segments | Main | ~01m40s",
+            "12:00:00.000 WARN : Decryption engine: MP4DECRYPT",
+            "12:07:00.000 INFO : Done",
+        ] {
+            assert_eq!(parse_phase(line), None, "{:?}", line);
+        }
+    }
+
+    #[test]
+    fn throttles_updates() {
+        let start = Instant::now();
+        let at = |millis| start + Duration::from_millis(millis);
+        let mut throttle = Throttle::new(Duration::from_millis(250));
+
+        assert!(!throttle.flush(at(0)));
+        assert!(throttle.ready(at(0)));
+        assert!(!throttle.ready(at(100)));
+        assert!(!throttle.ready(at(200)));
+        assert!(throttle.ready(at(250)));
+        assert!(!throttle.flush(at(260)));
+
+        // The last update of a burst is sent on flush and restarts the interval
+        assert!(!throttle.ready(at(300)));
+        assert!(throttle.flush(at(310)));
+        assert!(!throttle.flush(at(320)));
+        assert!(!throttle.ready(at(500)));
+        assert!(throttle.ready(at(560)));
+    }
+
+    #[derive(Default)]
+    struct RecordingSink {
+        phase: Option<DownloadPhase>,
+        // Phases and progress in the order they were sent, progress as its percentage
+        sent: Vec<String>,
+    }
+
+    impl ProgressSink for RecordingSink {
+        fn set_phase(&mut self, phase: DownloadPhase) -> Option<PhaseChange> {
+            if self.phase == Some(phase) {
+                return None;
+            }
+            self.phase = Some(phase);
+            Some(PhaseChange { phase, started_at: 0 })
+        }
+
+        fn send_phase(&mut self, change: PhaseChange) {
+            self.sent.push(format!("{:?}", change.phase));
+        }
+
+        fn set_progress(&mut self, _progress: &DownloadProgress) {}
+
+        fn send_progress(&mut self, progress: &DownloadProgress) {
+            self.sent.push(format!("{:.0}%", progress.overall.percentage));
+        }
+    }
+
+    #[test]
+    fn reader_throttles_progress_rows() {
+        let start = Instant::now();
+        let mut reader = ProgressReader::new(DownloadProgress::default(), Duration::from_millis(250));
+        let mut sink = RecordingSink::default();
+
+        reader.read_line("12:00:00.000 INFO : Loading URL: https://example.com/a.m3u8", start, &mut sink);
+        // 100 rows in a second
+        for row in 1..=100 {
+            let line = format!("Vid 1280x720 | 2500 Kbps | 25 ━━━━━━━━━━ {}/100 {}.00% - - --:--:--", row, row);
+            reader.read_line(&line, start + Duration::from_millis(row * 10), &mut sink);
+        }
+        reader.read_line("12:00:02.000 INFO : Binary merging...", start + Duration::from_millis(1005), &mut sink);
+        reader.finish(start + Duration::from_millis(1010), &mut sink);
+
+        assert_eq!(sink.sent, ["Parsing", "Downloading", "1%", "26%", "51%", "76%", "100%", "Merging"]);
+    }
+
+    #[test]
+    fn splits_at_carriage_returns() {
+        let output = "first\r\nsecond\rthird\nlast";
+        let lines: Vec<String> = OutputLines::new(std::io::BufReader::with_capacity(4, output.as_bytes())).collect();
+        assert_eq!(lines, ["first", "second", "third", "last"]);
+    }
+}
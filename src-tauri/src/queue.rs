@@ -0,0 +1,145 @@
+use serde::Serialize;
+
+#[derive(Debug, Serialize, Clone, PartialEq)]
+pub struct QueueEntry {
+    pub job_id: u64,
+    pub priority: i32,
+}
+
+// Jobs waiting for a free download slot. Entries are kept ordered by priority
+// (highest first) and in FIFO order among equal priorities.
+#[derive(Debug, Default)]
+pub struct DownloadQueue {
+    entries: Vec<QueueEntry>,
+}
+
+impl DownloadQueue {
+    pub fn push(&mut self, job_id: u64, priority: i32) {
+        let position = self.entries
+            .iter()
+            .position(|entry| entry.priority < priority)
+            .unwrap_or(self.entries.len());
+        self.entries.insert(position, QueueEntry { job_id, priority });
+    }
+
+    // Puts a job ahead of everything else, e.g. a resumed download
+    pub fn push_front(&mut self, job_id: u64) {
+        let priority = self.entries.first().map_or(0, |entry| entry.priority);
+        self.entries.insert(0, QueueEntry { job_id, priority });
+    }
+
+    pub fn pop(&mut self) -> Option<u64> {
+        if self.entries.is_empty() {
+            None
+        } else {
+            Some(self.entries.remove(0).job_id)
+        }
+    }
+
+    pub fn remove(&mut self, job_id: u64) -> bool {
+        let len = self.entries.len();
+        self.entries.retain(|entry| entry.job_id != job_id);
+        self.entries.len() != len
+    }
+
+    pub fn set_priority(&mut self, job_id: u64, priority: i32) -> Result<(), String> {
+        if !self.remove(job_id) {
+            return Err(format!("Job {} is not queued", job_id));
+        }
+        self.push(job_id, priority);
+        Ok(())
+    }
+
+    // Moves a job to `position`. It takes over the priority of the entry it
+    // lands behind (or in front of, at the head) so the order stays consistent.
+    pub fn move_to(&mut self, job_id: u64, position: usize) -> Result<(), String> {
+        let current = self.entries
+            .iter()
+            .position(|entry| entry.job_id == job_id)
+            .ok_or_else(|| format!("Job {} is not queued", job_id))?;
+        let mut entry = self.entries.remove(current);
+        let position = position.min(self.entries.len());
+        let neighbour = if position == 0 {
+            self.entries.first()
+        } else {
+            self.entries.get(position - 1)
+        };
+        if let Some(neighbour) = neighbour {
+            entry.priority = neighbour.priority;
+        }
+        self.entries.insert(position, entry);
+        Ok(())
+    }
+
+    pub fn entries(&self) -> Vec<QueueEntry> {
+        self.entries.clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ids(queue: &DownloadQueue) -> Vec<u64> {
+        queue.entries().iter().map(|entry| entry.job_id).collect()
+    }
+
+    #[test]
+    fn pops_in_fifo_order() {
+        let mut queue = DownloadQueue::default();
+        for job_id in 1..=3 {
+            queue.push(job_id, 0);
+        }
+        assert_eq!((queue.pop(), queue.pop(), queue.pop(), queue.pop()), (Some(1), Some(2), Some(3), None));
+    }
+
+    #[test]
+    fn orders_by_priority_then_arrival() {
+        let mut queue = DownloadQueue::default();
+        queue.push(1, 0);
+        queue.push(2, 5);
+        queue.push(3, 0);
+        queue.push(4, 5);
+        queue.push(5, -1);
+        assert_eq!(ids(&queue), [2, 4, 1, 3, 5]);
+
+        queue.set_priority(3, 10).unwrap();
+        assert_eq!(ids(&queue), [3, 2, 4, 1, 5]);
+        assert!(queue.set_priority(9, 1).is_err());
+
+        queue.push_front(6);
+        assert_eq!(queue.entries()[0], QueueEntry { job_id: 6, priority: 10 });
+    }
+
+    #[test]
+    fn moves_jobs_and_takes_over_the_neighbours_priority() {
+        let mut queue = DownloadQueue::default();
+        queue.push(1, 5);
+        queue.push(2, 0);
+        queue.push(3, 0);
+
+        queue.move_to(3, 0).unwrap();
+        assert_eq!(ids(&queue), [3, 1, 2]);
+        assert_eq!(queue.entries()[0].priority, 5);
+
+        // Positions past the end put the job last
+        queue.move_to(3, 10).unwrap();
+        assert_eq!(ids(&queue), [1, 2, 3]);
+        assert_eq!(queue.entries()[2].priority, 0);
+
+        // The order stays consistent for jobs pushed later
+        queue.push(4, 0);
+        assert_eq!(ids(&queue), [1, 2, 3, 4]);
+        assert!(queue.move_to(9, 0).is_err());
+    }
+
+    #[test]
+    fn removes_jobs() {
+        let mut queue = DownloadQueue::default();
+        queue.push(1, 0);
+        queue.push(2, 0);
+        assert!(queue.remove(1));
+        assert!(!queue.remove(1));
+        assert_eq!(ids(&queue), [2]);
+    }
+}
@@ -0,0 +1,413 @@
+use std::cmp::Ordering;
+use serde::{Deserialize, Serialize};
+
+use crate::streams::{AudioStream, StreamInfo, SubtitleStream, VideoStream};
+
+// Rules that pick streams without going through the pickers, e.g. "best video
+// up to 1080p, prefer HEVC, audio in ja then en, every zh* subtitle". A type
+// without a rule is left to N_m3u8DL-RE.
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq)]
+#[serde(default)]
+pub struct SelectionRules {
+    pub video: Option<VideoRule>,
+    pub audio: Option<TrackRule>,
+    pub subtitles: Option<TrackRule>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, Default, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum Pick {
+    #[default]
+    Best,
+    Worst,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq)]
+#[serde(default)]
+pub struct VideoRule {
+    pub pick: Pick,
+    // Limits on the picture height, streams without a known resolution always pass
+    pub max_height: Option<u32>,
+    pub min_height: Option<u32>,
+    // Preferred codecs, most wanted first. Only breaks ties between streams of
+    // the same resolution, so a preferred codec never costs resolution.
+    pub codecs: Vec<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq)]
+#[serde(default)]
+pub struct TrackRule {
+    // Language patterns, most wanted first. `*` matches any run of characters
+    // and a plain tag also matches its subtags ("en" matches "en-US"). Empty
+    // means any language.
+    pub languages: Vec<String>,
+    // Keep every matching track instead of the best one per language
+    pub all: bool,
+    pub pick: Pick,
+    pub codecs: Vec<String>,
+    pub limit: Option<usize>,
+}
+
+// Streams picked by the rules, in the shape `DownloadOptions` takes them
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Selection {
+    pub video: Option<VideoStream>,
+    pub audio_streams: Vec<AudioStream>,
+    pub subtitles: Vec<SubtitleStream>,
+}
+
+impl SelectionRules {
+    pub fn validate(&self) -> Result<(), String> {
+        if let Some(video) = &self.video {
+            if let (Some(min), Some(max)) = (video.min_height, video.max_height) {
+                if min > max {
+                    return Err(format!("video min_height {} is above max_height {}", min, max));
+                }
+            }
+        }
+        for (name, rule) in [("audio", &self.audio), ("subtitles", &self.subtitles)] {
+            if rule.as_ref().and_then(|rule| rule.limit) == Some(0) {
+                return Err(format!("{} limit must be at least 1", name));
+            }
+        }
+        Ok(())
+    }
+
+    pub fn resolve(&self, info: &StreamInfo) -> Result<Selection, String> {
+        let mut selection = Selection::default();
+
+        if let Some(rule) = &self.video {
+            let video = rule.resolve(&info.video_streams);
+            if video.is_none() && !info.video_streams.is_empty() {
+                return Err(format!(
+                    "None of the {} video streams is between {} and {} pixels high",
+                    info.video_streams.len(),
+                    rule.min_height.unwrap_or(0),
+                    rule.max_height.map_or("any".to_string(), |height| height.to_string()),
+                ));
+            }
+            selection.video = video;
+        }
+        if let Some(rule) = &self.audio {
+            selection.audio_streams = rule.resolve(&info.audio_streams, |audio| TrackKey {
+                language: audio.language.as_deref(),
+                codecs: audio.codecs.as_deref(),
+                channels: audio.channels.as_deref().and_then(channel_count),
+                bandwidth: audio.bandwidth,
+            });
+        }
+        if let Some(rule) = &self.subtitles {
+            selection.subtitles = rule.resolve(&info.subtitle_streams, |subtitle| TrackKey {
+                language: subtitle.language.as_deref(),
+                codecs: subtitle.codecs.as_deref(),
+                channels: None,
+                bandwidth: None,
+            });
+        }
+
+        Ok(selection)
+    }
+}
+
+impl VideoRule {
+    fn resolve(&self, streams: &[VideoStream]) -> Option<VideoStream> {
+        let candidates = streams.iter().filter(|video| match height(video) {
+            Some(height) => self.min_height.map_or(true, |min| height >= min)
+                && self.max_height.map_or(true, |max| height <= max),
+            None => true,
+        });
+        // Ranks the preferred stream highest, whichever way `pick` points.
+        // Streams of unknown resolution only win when nothing else is left.
+        let rank = |a: &VideoStream, b: &VideoStream| {
+            let known = height(a).is_some().cmp(&height(b).is_some());
+            let resolution = height(a).cmp(&height(b));
+            let bandwidth = a.bandwidth.cmp(&b.bandwidth);
+            let codec = codec_rank(&self.codecs, b.codecs.as_deref()).cmp(&codec_rank(&self.codecs, a.codecs.as_deref()));
+            known.then(match self.pick {
+                Pick::Best => resolution.then(codec).then(bandwidth),
+                Pick::Worst => resolution.reverse().then(codec).then(bandwidth.reverse()),
+            })
+        };
+        candidates.max_by(|a, b| rank(a, b)).cloned()
+    }
+}
+
+// What a track rule looks at in an audio or subtitle stream
+struct TrackKey<'a> {
+    language: Option<&'a str>,
+    codecs: Option<&'a str>,
+    channels: Option<u32>,
+    bandwidth: Option<u64>,
+}
+
+impl TrackRule {
+    fn resolve<'a, T: Clone>(&self, streams: &'a [T], key: impl Fn(&'a T) -> TrackKey<'a>) -> Vec<T> {
+        let any = ["*".to_string()];
+        let patterns = if self.languages.is_empty() { &any[..] } else { &self.languages[..] };
+        let rank = |a: &TrackKey, b: &TrackKey| {
+            let codec = codec_rank(&self.codecs, b.codecs).cmp(&codec_rank(&self.codecs, a.codecs));
+            let size = a.channels.cmp(&b.channels).then(a.bandwidth.cmp(&b.bandwidth));
+            codec.then(match self.pick {
+                Pick::Best => size,
+                Pick::Worst => size.reverse(),
+            })
+        };
+
+        let mut picked: Vec<usize> = Vec::new();
+        // One track per language, so "*" still yields every language once
+        let mut languages: Vec<Option<String>> = Vec::new();
+        for pattern in patterns {
+            let matches: Vec<usize> = (0..streams.len())
+                .filter(|&index| !picked.contains(&index))
+                .filter(|&index| match key(&streams[index]).language {
+                    Some(language) => matches_language(pattern, language),
+                    None => pattern == "*",
+                })
+                .collect();
+            if self.all {
+                picked.extend(matches);
+                continue;
+            }
+            for &index in &matches {
+                let language = key(&streams[index]).language.map(str::to_lowercase);
+                if languages.contains(&language) {
+                    continue;
+                }
+                let best = matches.iter()
+                    .copied()
+                    .filter(|&other| key(&streams[other]).language.map(str::to_lowercase) == language)
+                    .max_by(|&a, &b| match rank(&key(&streams[a]), &key(&streams[b])) {
+                        // Keep the first listed stream among equals
+                        Ordering::Equal => b.cmp(&a),
+                        ordering => ordering,
+                    });
+                picked.extend(best);
+                languages.push(language);
+            }
+        }
+
+        if let Some(limit) = self.limit {
+            picked.truncate(limit);
+        }
+        picked.into_iter().map(|index| streams[index].clone()).collect()
+    }
+}
+
+fn height(video: &VideoStream) -> Option<u32> {
+    video.resolution.as_deref()?.split_once('x')?.1.trim().parse().ok()
+}
+
+// "2", "6", "16/JOC" and "F801" style values, only the leading number counts
+fn channel_count(channels: &str) -> Option<u32> {
+    let digits: String = channels.chars().take_while(char::is_ascii_digit).collect();
+    digits.parse().ok()
+}
+
+// Lower is better, streams with none of the preferred codecs come last
+fn codec_rank(preferred: &[String], codecs: Option<&str>) -> usize {
+    let Some(codecs) = codecs.map(str::to_lowercase) else { return preferred.len() };
+    preferred.iter()
+        .position(|preference| {
+            codec_prefixes(preference).iter().any(|prefix| codecs.split(',').any(|codec| codec.trim().starts_with(prefix.as_str())))
+        })
+        .unwrap_or(preferred.len())
+}
+
+// Friendly names map onto the codec strings manifests use
+fn codec_prefixes(name: &str) -> Vec<String> {
+    let name = name.trim().to_lowercase();
+    let aliases: &[&str] = match name.as_str() {
+        "hevc" | "h265" | "h.265" => &["hvc1", "hev1"],
+        "avc" | "h264" | "h.264" => &["avc1", "avc3"],
+        "av1" => &["av01"],
+        "vp9" => &["vp09", "vp9"],
+        "aac" => &["mp4a.40"],
+        "eac3" | "e-ac-3" | "dolby digital plus" => &["ec-3"],
+        "ac3" | "ac-3" => &["ac-3"],
+        "opus" => &["opus"],
+        _ => return vec![name],
+    };
+    aliases.iter().map(|alias| alias.to_string()).collect()
+}
+
+fn matches_language(pattern: &str, language: &str) -> bool {
+    let (pattern, language) = (pattern.trim().to_lowercase(), language.trim().to_lowercase());
+    if !pattern.contains('*') {
+        return language == pattern || language.starts_with(&format!("{}-", pattern));
+    }
+    // Glob match where `*` is the only special character
+    let parts: Vec<&str> = pattern.split('*').collect();
+    let mut rest = language.as_str();
+    for (index, part) in parts.iter().enumerate() {
+        if index == 0 {
+            let Some(stripped) = rest.strip_prefix(part) else { return false };
+            rest = stripped;
+        } else if index == parts.len() - 1 {
+            return rest.ends_with(part);
+        } else {
+            let Some(position) = rest.find(part) else { return false };
+            rest = &rest[position + part.len()..];
+        }
+    }
+    rest.is_empty()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::streams::selector;
+
+    fn video(resolution: &str, bandwidth: u64, codecs: &str) -> VideoStream {
+        VideoStream {
+            id: format!("{}-{}-{}", resolution, bandwidth, codecs),
+            resolution: Some(resolution.to_string()),
+            bandwidth: Some(bandwidth),
+            codecs: Some(codecs.to_string()),
+            ..VideoStream::default()
+        }
+    }
+
+    fn audio(name: &str, language: &str, channels: &str, bandwidth: u64) -> AudioStream {
+        AudioStream {
+            id: name.to_string(),
+            group_id: Some("aud".to_string()),
+            name: Some(name.to_string()),
+            language: Some(language.to_string()),
+            channels: Some(channels.to_string()),
+            bandwidth: Some(bandwidth),
+            ..AudioStream::default()
+        }
+    }
+
+    fn subtitle(name: &str, language: &str) -> SubtitleStream {
+        SubtitleStream {
+            id: name.to_string(),
+            name: Some(name.to_string()),
+            language: Some(language.to_string()),
+            ..SubtitleStream::default()
+        }
+    }
+
+    fn sample() -> StreamInfo {
+        StreamInfo {
+            video_streams: vec![
+                video("3840x2160", 16_000_000, "hvc1.2.4.L153"),
+                video("1920x1080", 6_000_000, "avc1.640028"),
+                video("1920x1080", 4_000_000, "hvc1.2.4.L120"),
+                video("1280x720", 3_000_000, "avc1.64001f"),
+                video("640x360", 800_000, "avc1.4d401e"),
+            ],
+            audio_streams: vec![
+                audio("English", "en", "2", 128_000),
+                audio("English 5.1", "en", "6", 384_000),
+                audio("日本語", "ja", "2", 128_000),
+                audio("Français", "fr", "2", 128_000),
+            ],
+            subtitle_streams: vec![
+                subtitle("English", "en"),
+                subtitle("简体", "zh-Hans"),
+                subtitle("繁體", "zh-Hant"),
+                subtitle("粵語", "yue"),
+            ],
+            drm_only: false,
+        }
+    }
+
+    fn rules(json: serde_json::Value) -> SelectionRules {
+        serde_json::from_value(json).unwrap()
+    }
+
+    #[test]
+    fn picks_best_video_under_cap_preferring_codec() {
+        let selection = rules(serde_json::json!({
+            "video": { "max_height": 1080, "codecs": ["hevc"] },
+        })).resolve(&sample()).unwrap();
+        let video = selection.video.unwrap();
+        assert_eq!((video.resolution.as_deref(), video.bandwidth), (Some("1920x1080"), Some(4_000_000)));
+        assert_eq!(video.selector().as_deref(), Some(r"res=1920x1080:codecs=hvc1\.2\.4\.L120:bwMin=3999:bwMax=4001:for=best"));
+    }
+
+    #[test]
+    fn codec_preference_never_costs_resolution() {
+        let selection = rules(serde_json::json!({
+            "video": { "max_height": 720, "codecs": ["hevc"] },
+        })).resolve(&sample()).unwrap();
+        assert_eq!(selection.video.unwrap().resolution.as_deref(), Some("1280x720"));
+
+        let selection = rules(serde_json::json!({ "video": {} })).resolve(&sample()).unwrap();
+        assert_eq!(selection.video.unwrap().resolution.as_deref(), Some("3840x2160"));
+    }
+
+    #[test]
+    fn picks_worst_video_above_floor() {
+        let selection = rules(serde_json::json!({
+            "video": { "pick": "worst", "min_height": 720 },
+        })).resolve(&sample()).unwrap();
+        assert_eq!(selection.video.unwrap().resolution.as_deref(), Some("1280x720"));
+    }
+
+    #[test]
+    fn errors_when_no_video_fits() {
+        let error = rules(serde_json::json!({
+            "video": { "min_height": 4320 },
+        })).resolve(&sample()).unwrap_err();
+        assert!(error.contains("4320"), "{}", error);
+    }
+
+    #[test]
+    fn picks_best_audio_per_language_in_priority_order() {
+        let selection = rules(serde_json::json!({
+            "audio": { "languages": ["ja", "en"] },
+        })).resolve(&sample()).unwrap();
+        let names: Vec<_> = selection.audio_streams.iter().map(|a| a.name.as_deref().unwrap()).collect();
+        assert_eq!(names, ["日本語", "English 5.1"]);
+        assert_eq!(
+            selector(&selection.audio_streams).as_deref(),
+            Some(r"id=^aud$:name=^(日本語|English 5\.1)$:for=all"),
+        );
+
+        let selection = rules(serde_json::json!({
+            "audio": { "languages": ["de", "en"], "pick": "worst", "limit": 1 },
+        })).resolve(&sample()).unwrap();
+        assert_eq!(selection.audio_streams.len(), 1);
+        assert_eq!(selection.audio_streams[0].name.as_deref(), Some("English"));
+    }
+
+    #[test]
+    fn keeps_every_subtitle_matching_pattern() {
+        let selection = rules(serde_json::json!({
+            "subtitles": { "languages": ["zh*"], "all": true },
+        })).resolve(&sample()).unwrap();
+        let languages: Vec<_> = selection.subtitles.iter().map(|s| s.language.as_deref().unwrap()).collect();
+        assert_eq!(languages, ["zh-Hans", "zh-Hant"]);
+        assert_eq!(selector(&selection.subtitles).as_deref(), Some(r"name=^(简体|繁體)$:for=all"));
+    }
+
+    #[test]
+    fn types_without_rules_are_left_alone() {
+        let selection = SelectionRules::default().resolve(&sample()).unwrap();
+        assert_eq!(selection, Selection::default());
+
+        let selection = rules(serde_json::json!({ "audio": {} })).resolve(&sample()).unwrap();
+        assert_eq!(selection.audio_streams.len(), 3);
+        assert!(selection.video.is_none() && selection.subtitles.is_empty());
+    }
+
+    #[test]
+    fn language_patterns() {
+        assert!(matches_language("en", "en-US"));
+        assert!(matches_language("EN", "en"));
+        assert!(!matches_language("en", "eng"));
+        assert!(matches_language("zh*", "zh-Hans"));
+        assert!(matches_language("*-hant", "zh-Hant"));
+        assert!(matches_language("z*h*t", "zh-Hant"));
+        assert!(!matches_language("zh*", "yue"));
+    }
+
+    #[test]
+    fn validates_rules() {
+        assert!(rules(serde_json::json!({ "video": { "min_height": 1080, "max_height": 720 } })).validate().is_err());
+        assert!(rules(serde_json::json!({ "subtitles": { "limit": 0 } })).validate().is_err());
+        assert!(rules(serde_json::json!({ "video": { "max_height": 1080 } })).validate().is_ok());
+    }
+}
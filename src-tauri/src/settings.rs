@@ -0,0 +1,658 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::PathBuf;
+use std::time::Duration;
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use tauri::api::path;
+
+use crate::args::{LogLevel, MuxFormat, N_m3u8DLArgs, SubFormat};
+use crate::discovery::DiscoveryMode;
+use crate::jobs::now_millis;
+use crate::selection::SelectionRules;
+
+// Bump together with a new step in `migrate`
+pub const SETTINGS_VERSION: u32 = 3;
+
+// Used when a profile sets no `max_progress_rate`
+pub const DEFAULT_PROGRESS_RATE: u32 = 4;
+
+// Profile that exists from the start and that older settings files migrate into
+pub const DEFAULT_PROFILE: &str = "Default";
+
+// One named set of download settings
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq)]
+pub struct Settings {
+    pub download_location: Option<String>,
+    pub default_format: Option<MuxFormat>,
+    pub auto_start: Option<bool>,
+    pub concurrent_downloads: Option<u32>,
+    pub timeout: Option<u32>,
+    pub debug_mode: Option<bool>,
+    pub tmp_dir: Option<String>,
+    pub thread_count: Option<u32>,
+    pub download_retry_count: Option<u32>,
+    pub check_segments_count: Option<bool>,
+    pub binary_merge: Option<bool>,
+    pub use_ffmpeg_concat_demuxer: Option<bool>,
+    pub del_after_done: Option<bool>,
+    pub no_date_info: Option<bool>,
+    pub no_log: Option<bool>,
+    pub write_meta_json: Option<bool>,
+    pub append_url_params: Option<bool>,
+    pub concurrent_download: Option<bool>,
+    pub sub_only: Option<bool>,
+    pub sub_format: Option<SubFormat>,
+    pub auto_subtitle_fix: Option<bool>,
+    pub log_level: Option<LogLevel>,
+    pub use_system_proxy: Option<bool>,
+    #[serde(default)]
+    pub discovery_mode: Option<DiscoveryMode>,
+    // Picks streams for downloads that arrive without a selection
+    #[serde(default)]
+    pub selection_rules: Option<SelectionRules>,
+    // Most `download-progress` events per second for one download
+    #[serde(default)]
+    pub max_progress_rate: Option<u32>,
+}
+
+impl Settings {
+    // Returns these settings with every field that is set in `overrides` replaced
+    pub fn merged_with(&self, overrides: &Settings) -> Result<Settings, String> {
+        let mut merged = serde_json::to_value(self).map_err(|e| e.to_string())?;
+        let overrides = serde_json::to_value(overrides).map_err(|e| e.to_string())?;
+        if let (Some(merged), Some(overrides)) = (merged.as_object_mut(), overrides.as_object()) {
+            for (key, value) in overrides {
+                if !value.is_null() {
+                    merged.insert(key.clone(), value.clone());
+                }
+            }
+        }
+        serde_json::from_value(merged).map_err(|e| e.to_string())
+    }
+
+    // Checks the ranges N_m3u8DL-RE and the queue can work with
+    pub fn validate(&self) -> Result<(), String> {
+        let mut errors = Vec::new();
+        let mut check_range = |name: &str, value: Option<u32>, min: u32, max: u32| {
+            if let Some(value) = value {
+                if value < min || value > max {
+                    errors.push(format!("{} must be between {} and {}, got {}", name, min, max, value));
+                }
+            }
+        };
+        check_range("thread_count", self.thread_count, 1, 256);
+        check_range("download_retry_count", self.download_retry_count, 0, 100);
+        check_range("concurrent_downloads", self.concurrent_downloads, 1, 16);
+        check_range("timeout", self.timeout, 1, 3600);
+        check_range("max_progress_rate", self.max_progress_rate, 1, 60);
+
+        for (name, value) in [("download_location", &self.download_location), ("tmp_dir", &self.tmp_dir)] {
+            if value.as_deref().is_some_and(|dir| dir.trim().is_empty()) {
+                errors.push(format!("{} must not be empty", name));
+            }
+        }
+
+        if let Some(Err(e)) = self.selection_rules.as_ref().map(SelectionRules::validate) {
+            errors.push(format!("selection_rules: {}", e));
+        }
+
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(format!("Invalid settings: {}", errors.join("; ")))
+        }
+    }
+
+    // Whether new downloads start on their own. When off they wait in the
+    // queue until `start_queue`.
+    pub fn auto_start_enabled(&self) -> bool {
+        self.auto_start.unwrap_or(true)
+    }
+
+    pub fn max_concurrent_downloads(&self) -> usize {
+        self.concurrent_downloads.unwrap_or(1).max(1) as usize
+    }
+
+    // Shortest time between two progress events of a download
+    pub fn progress_interval(&self) -> Duration {
+        Duration::from_millis(1000 / self.max_progress_rate.unwrap_or(DEFAULT_PROGRESS_RATE).max(1) as u64)
+    }
+}
+
+// Contents of the settings file: every profile plus the one used when a
+// download does not name a profile
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct SettingsStore {
+    // Layout version of the settings file, written by `save_settings`
+    #[serde(default)]
+    pub version: u32,
+    pub active_profile: String,
+    pub profiles: BTreeMap<String, Settings>,
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct ProfileList {
+    pub active_profile: String,
+    pub profiles: Vec<String>,
+}
+
+impl Default for SettingsStore {
+    fn default() -> Self {
+        SettingsStore {
+            version: SETTINGS_VERSION,
+            active_profile: DEFAULT_PROFILE.to_string(),
+            profiles: BTreeMap::from([(DEFAULT_PROFILE.to_string(), Settings::default())]),
+        }
+    }
+}
+
+impl SettingsStore {
+    pub fn active(&self) -> &Settings {
+        &self.profiles[&self.active_profile]
+    }
+
+    // Settings of the named profile, or of the active one when no name is given
+    pub fn profile(&self, name: Option<&str>) -> Result<&Settings, String> {
+        match name {
+            Some(name) => self.profiles
+                .get(name)
+                .ok_or_else(|| format!("Unknown settings profile: {}", name)),
+            None => Ok(self.active()),
+        }
+    }
+
+    pub fn set_active(&mut self, settings: Settings) {
+        self.profiles.insert(self.active_profile.clone(), settings);
+    }
+
+    pub fn list(&self) -> ProfileList {
+        ProfileList {
+            active_profile: self.active_profile.clone(),
+            profiles: self.profiles.keys().cloned().collect(),
+        }
+    }
+
+    pub fn create(&mut self, name: &str, settings: Settings) -> Result<(), String> {
+        let name = name.trim();
+        if name.is_empty() {
+            return Err("Profile name must not be empty".to_string());
+        }
+        if self.profiles.contains_key(name) {
+            return Err(format!("Settings profile {} already exists", name));
+        }
+        settings.validate()?;
+        self.profiles.insert(name.to_string(), settings);
+        Ok(())
+    }
+
+    pub fn clone_profile(&mut self, source: &str, name: &str) -> Result<(), String> {
+        let settings = self.profile(Some(source))?.clone();
+        self.create(name, settings)
+    }
+
+    pub fn delete(&mut self, name: &str) -> Result<(), String> {
+        if name == self.active_profile {
+            return Err(format!("Cannot delete the active settings profile {}", name));
+        }
+        self.profiles
+            .remove(name)
+            .map(|_| ())
+            .ok_or_else(|| format!("Unknown settings profile: {}", name))
+    }
+
+    pub fn switch(&mut self, name: &str) -> Result<(), String> {
+        self.profile(Some(name))?;
+        self.active_profile = name.to_string();
+        Ok(())
+    }
+
+    // Hand-edited files may point at a profile that does not exist
+    fn repair(&mut self) {
+        if self.profiles.is_empty() {
+            self.profiles.insert(DEFAULT_PROFILE.to_string(), Settings::default());
+        }
+        if !self.profiles.contains_key(&self.active_profile) {
+            let fallback = self.profiles.keys().next().cloned().unwrap();
+            println!("Active profile {} does not exist, switching to {}", self.active_profile, fallback);
+            self.active_profile = fallback;
+        }
+    }
+}
+
+pub fn get_settings_path() -> PathBuf {
+    let app_dir = path::app_data_dir(&tauri::Config::default()).unwrap();
+    println!("Settings directory: {:?}", app_dir);
+    let settings_path = app_dir.join("settings.json");
+    println!("Settings file path: {:?}", settings_path);
+    settings_path
+}
+
+pub fn load_settings() -> SettingsStore {
+    let settings_path = get_settings_path();
+    println!("Loading settings from: {:?}", settings_path);
+    let contents = match fs::read_to_string(&settings_path) {
+        Ok(contents) => contents,
+        Err(_) => {
+            println!("No settings found, using defaults");
+            return SettingsStore::default();
+        }
+    };
+    println!("Settings file contents: {}", contents);
+
+    let parsed = serde_json::from_str::<Value>(&contents)
+        .map_err(|e| e.to_string())
+        .and_then(|value| {
+            let version = file_version(&value);
+            let mut store: SettingsStore = serde_json::from_value(migrate(value)).map_err(|e| e.to_string())?;
+            store.repair();
+            Ok((version, store))
+        });
+
+    match parsed {
+        Ok((version, store)) => {
+            println!("Successfully loaded settings: {:?}", store);
+            if version < SETTINGS_VERSION {
+                println!("Migrated settings from version {} to {}", version, SETTINGS_VERSION);
+                if let Err(e) = save_settings(&store) {
+                    println!("Failed to write migrated settings: {}", e);
+                }
+            }
+            store
+        }
+        Err(e) => {
+            // Keep the file around instead of silently losing the user's configuration
+            let backup_path = settings_path.with_extension(format!("json.bak-{}", now_millis()));
+            println!("Failed to parse settings ({}), moving them to {:?} and using defaults", e, backup_path);
+            if let Err(e) = fs::rename(&settings_path, &backup_path) {
+                println!("Failed to back up settings file: {:?}", e);
+            }
+            SettingsStore::default()
+        }
+    }
+}
+
+pub fn save_settings(store: &SettingsStore) -> Result<(), String> {
+    let settings_path = get_settings_path();
+    println!("Saving settings to: {:?}", settings_path);
+    if let Some(parent) = settings_path.parent() {
+        fs::create_dir_all(parent).map_err(|e| {
+            println!("Failed to create settings directory: {:?}", e);
+            e.to_string()
+        })?;
+    }
+    let store = SettingsStore { version: SETTINGS_VERSION, ..store.clone() };
+    let contents = serde_json::to_string_pretty(&store).map_err(|e| {
+        println!("Failed to serialize settings: {:?}", e);
+        e.to_string()
+    })?;
+    println!("Settings to save: {}", contents);
+    fs::write(&settings_path, contents).map_err(|e| {
+        println!("Failed to write settings file: {:?}", e);
+        e.to_string()
+    })
+}
+
+// Files written before versioning have no `version` field
+fn file_version(value: &Value) -> u32 {
+    value.get("version").and_then(Value::as_u64).unwrap_or(1) as u32
+}
+
+// Brings a settings file of any earlier layout up to `SETTINGS_VERSION`
+fn migrate(mut value: Value) -> Value {
+    let version = file_version(&value);
+    let Some(object) = value.as_object_mut() else { return value };
+
+    if version < 2 {
+        // Version 1 stored numbers as strings and enums in free-form case.
+        // Values that cannot be converted are dropped so they fall back to defaults.
+        for key in ["concurrent_downloads", "timeout", "thread_count", "download_retry_count"] {
+            let number = match object.get(key) {
+                Some(Value::String(text)) => text.trim().parse::<u32>().ok().map(Value::from),
+                Some(Value::Number(number)) => number.as_u64().map(Value::from),
+                _ => None,
+            };
+            match number {
+                Some(number) => object.insert(key.to_string(), number),
+                None => object.remove(key),
+            };
+        }
+
+        let enums: [(&str, &[&str], bool); 3] = [
+            ("sub_format", &["SRT", "VTT"], true),
+            ("log_level", &["DEBUG", "INFO", "WARN", "ERROR", "OFF"], true),
+            ("default_format", &["mkv", "mp4", "ts"], false),
+        ];
+        for (key, allowed, uppercase) in enums {
+            let normalized = object.get(key)
+                .and_then(Value::as_str)
+                .map(|text| if uppercase { text.trim().to_uppercase() } else { text.trim().to_lowercase() })
+                .filter(|text| allowed.contains(&text.as_str()));
+            match normalized {
+                Some(text) => object.insert(key.to_string(), Value::from(text)),
+                None => object.remove(key),
+            };
+        }
+    }
+
+    if version < 3 {
+        // Version 2 held a single set of settings, which becomes the default profile
+        object.remove("version");
+        let settings = Value::Object(std::mem::take(object));
+        let mut profiles = Map::new();
+        profiles.insert(DEFAULT_PROFILE.to_string(), settings);
+        object.insert("active_profile".to_string(), Value::from(DEFAULT_PROFILE));
+        object.insert("profiles".to_string(), Value::Object(profiles));
+    }
+
+    object.insert("version".to_string(), Value::from(SETTINGS_VERSION));
+    value
+}
+
+// Maps every download-related setting onto its N_m3u8DL-RE flag. Directories,
+// thread count, retry count, subtitle format and log level fall back to the
+// app's own defaults; the other flags are left out when unset so
+// N_m3u8DL-RE's defaults apply.
+pub fn apply_settings(args: N_m3u8DLArgs, settings: &Settings) -> N_m3u8DLArgs {
+    let save_dir = settings.download_location.clone().unwrap_or_else(|| {
+        path::download_dir().unwrap().join("m3u8").to_string_lossy().into_owned()
+    });
+    let tmp_dir = settings.tmp_dir.clone().unwrap_or_else(|| {
+        path::download_dir().unwrap().join("m3u8/Temp").to_string_lossy().into_owned()
+    });
+    // Debug mode wins over the configured log level
+    let log_level = if settings.debug_mode == Some(true) {
+        LogLevel::Debug
+    } else {
+        settings.log_level.unwrap_or(LogLevel::Info)
+    };
+
+    let mut args = args
+        .save_dir(save_dir)
+        .tmp_dir(tmp_dir)
+        .thread_count(settings.thread_count.unwrap_or(16))
+        .download_retry_count(settings.download_retry_count.unwrap_or(3))
+        .sub_format(settings.sub_format.unwrap_or(SubFormat::Srt))
+        .log_level(log_level)
+        .binary_merge(settings.binary_merge == Some(true))
+        .use_ffmpeg_concat_demuxer(settings.use_ffmpeg_concat_demuxer == Some(true))
+        .no_date_info(settings.no_date_info == Some(true))
+        .no_log(settings.no_log == Some(true))
+        .append_url_params(settings.append_url_params == Some(true))
+        .concurrent_download(settings.concurrent_download == Some(true))
+        .sub_only(settings.sub_only == Some(true))
+        .force_ansi_console(true);
+
+    // These default to true in N_m3u8DL-RE, so they are passed with an explicit value
+    if let Some(enabled) = settings.check_segments_count {
+        args = args.check_segments_count(enabled);
+    }
+    if let Some(enabled) = settings.del_after_done {
+        args = args.del_after_done(enabled);
+    }
+    if let Some(enabled) = settings.write_meta_json {
+        args = args.write_meta_json(enabled);
+    }
+    if let Some(enabled) = settings.auto_subtitle_fix {
+        args = args.auto_subtitle_fix(enabled);
+    }
+    if let Some(enabled) = settings.use_system_proxy {
+        args = args.use_system_proxy(enabled);
+    }
+    if let Some(timeout) = settings.timeout {
+        args = args.http_request_timeout(timeout);
+    }
+    args
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn settings() -> Settings {
+        Settings {
+            download_location: Some("/downloads".to_string()),
+            tmp_dir: Some("/downloads/Temp".to_string()),
+            ..Settings::default()
+        }
+    }
+
+    fn args_for(settings: &Settings) -> Vec<String> {
+        apply_settings(N_m3u8DLArgs::new("in.m3u8"), settings).to_args()
+    }
+
+    fn flag_value<'a>(args: &'a [String], flag: &str) -> Option<&'a str> {
+        args.iter()
+            .position(|arg| arg == flag)
+            .and_then(|i| args.get(i + 1))
+            .map(String::as_str)
+    }
+
+    fn has_flag(args: &[String], flag: &str) -> bool {
+        args.iter().any(|arg| arg == flag)
+    }
+
+    #[test]
+    fn defaults() {
+        assert_eq!(args_for(&settings()), [
+            "in.m3u8",
+            "--save-dir", "/downloads",
+            "--tmp-dir", "/downloads/Temp",
+            "--thread-count", "16",
+            "--download-retry-count", "3",
+            "--sub-format", "SRT",
+            "--log-level", "INFO",
+            "--force-ansi-console",
+        ]);
+    }
+
+    #[test]
+    fn check_segments_count() {
+        let args = args_for(&Settings { check_segments_count: Some(false), ..settings() });
+        assert_eq!(flag_value(&args, "--check-segments-count"), Some("false"));
+        let args = args_for(&Settings { check_segments_count: Some(true), ..settings() });
+        assert_eq!(flag_value(&args, "--check-segments-count"), Some("true"));
+    }
+
+    #[test]
+    fn binary_merge() {
+        assert!(has_flag(&args_for(&Settings { binary_merge: Some(true), ..settings() }), "--binary-merge"));
+        assert!(!has_flag(&args_for(&Settings { binary_merge: Some(false), ..settings() }), "--binary-merge"));
+    }
+
+    #[test]
+    fn use_ffmpeg_concat_demuxer() {
+        let args = args_for(&Settings { use_ffmpeg_concat_demuxer: Some(true), ..settings() });
+        assert!(has_flag(&args, "--use-ffmpeg-concat-demuxer"));
+    }
+
+    #[test]
+    fn del_after_done() {
+        let args = args_for(&Settings { del_after_done: Some(false), ..settings() });
+        assert_eq!(flag_value(&args, "--del-after-done"), Some("false"));
+    }
+
+    #[test]
+    fn no_date_info() {
+        assert!(has_flag(&args_for(&Settings { no_date_info: Some(true), ..settings() }), "--no-date-info"));
+    }
+
+    #[test]
+    fn no_log() {
+        assert!(has_flag(&args_for(&Settings { no_log: Some(true), ..settings() }), "--no-log"));
+    }
+
+    #[test]
+    fn write_meta_json() {
+        let args = args_for(&Settings { write_meta_json: Some(false), ..settings() });
+        assert_eq!(flag_value(&args, "--write-meta-json"), Some("false"));
+    }
+
+    #[test]
+    fn append_url_params() {
+        let args = args_for(&Settings { append_url_params: Some(true), ..settings() });
+        assert!(has_flag(&args, "--append-url-params"));
+    }
+
+    #[test]
+    fn concurrent_download() {
+        let args = args_for(&Settings { concurrent_download: Some(true), ..settings() });
+        assert!(has_flag(&args, "--concurrent-download"));
+    }
+
+    #[test]
+    fn sub_only() {
+        assert!(has_flag(&args_for(&Settings { sub_only: Some(true), ..settings() }), "--sub-only"));
+    }
+
+    #[test]
+    fn auto_subtitle_fix() {
+        let args = args_for(&Settings { auto_subtitle_fix: Some(false), ..settings() });
+        assert_eq!(flag_value(&args, "--auto-subtitle-fix"), Some("false"));
+    }
+
+    #[test]
+    fn use_system_proxy() {
+        let args = args_for(&Settings { use_system_proxy: Some(false), ..settings() });
+        assert_eq!(flag_value(&args, "--use-system-proxy"), Some("false"));
+    }
+
+    #[test]
+    fn timeout() {
+        let args = args_for(&Settings { timeout: Some(45), ..settings() });
+        assert_eq!(flag_value(&args, "--http-request-timeout"), Some("45"));
+        assert!(!has_flag(&args_for(&settings()), "--http-request-timeout"));
+    }
+
+    #[test]
+    fn debug_mode() {
+        let args = args_for(&Settings {
+            debug_mode: Some(true),
+            log_level: Some(LogLevel::Warn),
+            ..settings()
+        });
+        assert_eq!(flag_value(&args, "--log-level"), Some("DEBUG"));
+        let args = args_for(&Settings { log_level: Some(LogLevel::Warn), ..settings() });
+        assert_eq!(flag_value(&args, "--log-level"), Some("WARN"));
+    }
+
+    #[test]
+    fn validate_accepts_defaults_and_reports_every_error() {
+        assert_eq!(Settings::default().validate(), Ok(()));
+        let error = Settings {
+            thread_count: Some(0),
+            concurrent_downloads: Some(40),
+            tmp_dir: Some("  ".to_string()),
+            ..Settings::default()
+        }.validate().unwrap_err();
+        assert!(error.contains("thread_count must be between 1 and 256, got 0"), "{}", error);
+        assert!(error.contains("concurrent_downloads must be between 1 and 16, got 40"), "{}", error);
+        assert!(error.contains("tmp_dir must not be empty"), "{}", error);
+    }
+
+    #[test]
+    fn progress_interval() {
+        assert_eq!(Settings::default().progress_interval(), Duration::from_millis(250));
+        assert_eq!(Settings { max_progress_rate: Some(10), ..Settings::default() }.progress_interval(), Duration::from_millis(100));
+        let error = Settings { max_progress_rate: Some(0), ..Settings::default() }.validate().unwrap_err();
+        assert!(error.contains("max_progress_rate must be between 1 and 60, got 0"), "{}", error);
+    }
+
+    #[test]
+    fn auto_start_is_on_unless_turned_off() {
+        assert!(Settings::default().auto_start_enabled());
+        assert!(!Settings { auto_start: Some(false), ..Settings::default() }.auto_start_enabled());
+    }
+
+    #[test]
+    fn migrates_version_1_layout() {
+        let v1 = serde_json::json!({
+            "download_location": "/downloads",
+            "default_format": "MP4",
+            "auto_start": true,
+            "concurrent_downloads": "2",
+            "timeout": 30,
+            "thread_count": "sixteen",
+            "download_retry_count": " 5 ",
+            "sub_format": "vtt",
+            "log_level": "verbose",
+        });
+        let store: SettingsStore = serde_json::from_value(migrate(v1)).unwrap();
+        assert_eq!(store.version, SETTINGS_VERSION);
+        assert_eq!(store.active_profile, DEFAULT_PROFILE);
+        assert_eq!(store.active(), &Settings {
+            download_location: Some("/downloads".to_string()),
+            default_format: Some(MuxFormat::Mp4),
+            auto_start: Some(true),
+            concurrent_downloads: Some(2),
+            timeout: Some(30),
+            download_retry_count: Some(5),
+            sub_format: Some(SubFormat::Vtt),
+            ..Settings::default()
+        });
+    }
+
+    #[test]
+    fn migrates_version_2_into_default_profile() {
+        let v2 = serde_json::json!({
+            "version": 2,
+            "thread_count": 8,
+            "log_level": "ERROR",
+        });
+        let store: SettingsStore = serde_json::from_value(migrate(v2)).unwrap();
+        assert_eq!(store.profiles.len(), 1);
+        assert_eq!(store.active(), &Settings {
+            thread_count: Some(8),
+            log_level: Some(LogLevel::Error),
+            ..Settings::default()
+        });
+    }
+
+    #[test]
+    fn current_layout_round_trips() {
+        let mut store = SettingsStore::default();
+        store.create("Anime", Settings { thread_count: Some(8), ..Settings::default() }).unwrap();
+        store.switch("Anime").unwrap();
+        let value = serde_json::to_value(&store).unwrap();
+        assert_eq!(serde_json::from_value::<SettingsStore>(migrate(value)).unwrap(), store);
+    }
+
+    #[test]
+    fn profiles() {
+        let mut store = SettingsStore::default();
+        store.set_active(Settings { thread_count: Some(4), ..Settings::default() });
+        store.clone_profile(DEFAULT_PROFILE, " Proxy ").unwrap();
+        assert_eq!(store.profile(Some("Proxy")).unwrap().thread_count, Some(4));
+        assert!(store.create("Proxy", Settings::default()).is_err());
+        assert!(store.create("", Settings::default()).is_err());
+        assert!(store.create("Broken", Settings { thread_count: Some(0), ..Settings::default() }).is_err());
+
+        store.switch("Proxy").unwrap();
+        assert_eq!(store.profile(None).unwrap(), store.profile(Some("Proxy")).unwrap());
+        assert!(store.delete("Proxy").is_err());
+        store.delete(DEFAULT_PROFILE).unwrap();
+        assert!(store.switch(DEFAULT_PROFILE).is_err());
+        assert!(store.profile(Some(DEFAULT_PROFILE)).is_err());
+        assert_eq!(store.list().profiles, ["Proxy"]);
+    }
+
+    #[test]
+    fn repairs_missing_active_profile() {
+        let mut store: SettingsStore = serde_json::from_value(serde_json::json!({
+            "version": SETTINGS_VERSION,
+            "active_profile": "Gone",
+            "profiles": { "Work": {} },
+        })).unwrap();
+        store.repair();
+        assert_eq!(store.active_profile, "Work");
+    }
+
+    #[test]
+    fn merged_with_keeps_unset_fields() {
+        let base = Settings { thread_count: Some(8), timeout: Some(10), ..Settings::default() };
+        let overrides = Settings { timeout: Some(60), ..Settings::default() };
+        let merged = base.merged_with(&overrides).unwrap();
+        assert_eq!(merged.thread_count, Some(8));
+        assert_eq!(merged.timeout, Some(60));
+    }
+}
@@ -0,0 +1,583 @@
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Deserializer, Serialize};
+use serde_json::Value;
+
+// Streams found in a manifest. Every stream carries an `id` that is derived
+// from what identifies it in the manifest, so it stays the same when the
+// manifest is loaded again.
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq)]
+pub struct StreamInfo {
+    pub video_streams: Vec<VideoStream>,
+    pub audio_streams: Vec<AudioStream>,
+    pub subtitle_streams: Vec<SubtitleStream>,
+    // Every video and audio stream needs a DRM license. Only a warning, since
+    // N_m3u8DL-RE can still download them with the right keys.
+    #[serde(default)]
+    pub drm_only: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq)]
+#[serde(default)]
+pub struct VideoStream {
+    pub id: String,
+    // What N_m3u8DL-RE calls the stream's GroupId
+    pub group_id: Option<String>,
+    pub resolution: Option<String>,
+    // Bits per second
+    pub bandwidth: Option<u64>,
+    pub frame_rate: Option<f64>,
+    pub codecs: Option<String>,
+    pub url: Option<String>,
+    // HLS: renditions that play with this variant
+    pub audio_group: Option<String>,
+    pub subtitle_group: Option<String>,
+    // DASH: index of the Period the stream belongs to
+    pub period: Option<usize>,
+    pub role: Option<String>,
+    pub encrypted: bool,
+    // Only known once the stream's own playlist has been read, which native
+    // discovery does not do for HLS
+    pub segment_count: Option<usize>,
+    // Seconds
+    pub duration: Option<f64>,
+    // Segment encryption as N_m3u8DL-RE names it, e.g. AES_128 or CENC
+    pub encryption: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq)]
+#[serde(default)]
+pub struct AudioStream {
+    pub id: String,
+    pub group_id: Option<String>,
+    pub name: Option<String>,
+    pub language: Option<String>,
+    pub channels: Option<String>,
+    pub bandwidth: Option<u64>,
+    pub codecs: Option<String>,
+    pub url: Option<String>,
+    pub period: Option<usize>,
+    pub role: Option<String>,
+    pub encrypted: bool,
+    pub segment_count: Option<usize>,
+    pub duration: Option<f64>,
+    pub encryption: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq)]
+#[serde(default)]
+pub struct SubtitleStream {
+    pub id: String,
+    pub group_id: Option<String>,
+    pub name: Option<String>,
+    pub language: Option<String>,
+    pub codecs: Option<String>,
+    pub url: Option<String>,
+    pub period: Option<usize>,
+    pub role: Option<String>,
+    pub segment_count: Option<usize>,
+    pub duration: Option<f64>,
+}
+
+impl VideoStream {
+    // N_m3u8DL-RE's -sv filter for this stream. The stream is matched by its
+    // identity where the manifest gives it one: the representation ID for DASH
+    // and Smooth Streaming, the variant URL for HLS. Only streams from the
+    // N_m3u8DL-RE log fall back to their attributes. `for=best` keeps it to one
+    // stream if the filter still matches several.
+    pub fn selector(&self) -> Option<String> {
+        let mut filters = Vec::new();
+        if let Some(group_id) = self.group_id.as_deref().and_then(non_empty) {
+            filters.push(format!("id=^{}$", escape_value(&group_id)));
+        } else if self.url.is_some() {
+            push_filter(&mut filters, "url", self.url.as_deref());
+        } else {
+            push_filter(&mut filters, "res", self.resolution.as_deref());
+            push_filter(&mut filters, "frame", self.frame_rate.map(|rate| rate.to_string()).as_deref());
+            push_filter(&mut filters, "codecs", self.codecs.as_deref());
+        }
+        // Smooth Streaming quality levels share their ID, the bitrate tells them apart
+        if let Some(bandwidth) = self.bandwidth.filter(|bandwidth| *bandwidth > 0) {
+            // The filter works in Kbps
+            let kbps = bandwidth / 1000;
+            filters.push(format!("bwMin={}:bwMax={}", kbps.saturating_sub(1), kbps + 1));
+        }
+        if filters.is_empty() {
+            return None;
+        }
+        filters.push("for=best".to_string());
+        join_filters(filters)
+    }
+
+    // Short description for log lines and errors
+    pub fn label(&self) -> String {
+        let mut parts = Vec::new();
+        parts.extend(self.resolution.clone());
+        parts.extend(self.bandwidth.map(|bandwidth| format!("{} Kbps", bandwidth / 1000)));
+        parts.extend(self.codecs.clone());
+        if parts.is_empty() {
+            self.id.clone()
+        } else {
+            parts.join(" ")
+        }
+    }
+}
+
+// Finds `wanted` among freshly discovered streams: by ID, then by URL, then by
+// representation, and last by its attributes for IDs from older versions. A URL
+// only counts when no other stream has it, since every DASH stream in
+// N_m3u8DL-RE's meta JSON has the manifest URL.
+pub fn find_video<'a>(streams: &'a [VideoStream], wanted: &VideoStream) -> Option<&'a VideoStream> {
+    let same_attributes = |video: &&VideoStream| {
+        video.resolution == wanted.resolution
+            && video.bandwidth == wanted.bandwidth
+            && (wanted.frame_rate.is_none() || video.frame_rate == wanted.frame_rate)
+            && (wanted.codecs.is_none() || video.codecs == wanted.codecs)
+    };
+    streams.iter().find(|video| video.id == wanted.id)
+        .or_else(|| {
+            let url = wanted.url.as_ref()?;
+            let mut same_url = streams.iter().filter(|video| video.url.as_ref() == Some(url));
+            match (same_url.next(), same_url.next()) {
+                (Some(video), None) => Some(video),
+                _ => None,
+            }
+        })
+        .or_else(|| {
+            wanted.group_id.as_ref()?;
+            streams.iter().find(|video| {
+                video.group_id == wanted.group_id && video.bandwidth == wanted.bandwidth && video.period == wanted.period
+            })
+        })
+        .or_else(|| streams.iter().find(same_attributes))
+}
+
+// Streams that can be picked together with one -sa/-ss filter
+pub trait Selectable {
+    // (filter key, value) pairs N_m3u8DL-RE can match the stream by
+    fn filter_values(&self) -> Vec<(&'static str, Option<&str>)>;
+}
+
+impl Selectable for AudioStream {
+    fn filter_values(&self) -> Vec<(&'static str, Option<&str>)> {
+        vec![
+            ("id", self.group_id.as_deref()),
+            ("name", self.name.as_deref()),
+            ("lang", self.language.as_deref()),
+            ("ch", self.channels.as_deref()),
+        ]
+    }
+}
+
+impl Selectable for SubtitleStream {
+    fn filter_values(&self) -> Vec<(&'static str, Option<&str>)> {
+        vec![
+            ("id", self.group_id.as_deref()),
+            ("name", self.name.as_deref()),
+            ("lang", self.language.as_deref()),
+        ]
+    }
+}
+
+// Builds one filter that matches all of `streams`. N_m3u8DL-RE takes a single
+// -sa/-ss filter and ANDs its keys, so alternating several keys would also
+// match mixes of the picked values, e.g. "English" and "日本語 5.1" would pull
+// in "English 5.1". Only one key alternates: the first whose value tells
+// every picked stream apart. Keys the streams share are added as they are.
+// Values are anchored so `en` does not match `en-GB`. `for=all` makes
+// N_m3u8DL-RE keep every match instead of only the best one.
+pub fn selector<T: Selectable>(streams: &[T]) -> Option<String> {
+    let mut values: Vec<Vec<(&'static str, Option<&str>)>> = Vec::new();
+    for stream in streams {
+        let stream_values = stream
+            .filter_values()
+            .into_iter()
+            .map(|(key, value)| (key, value.map(str::trim).filter(|value| !value.is_empty())))
+            .collect();
+        if !values.contains(&stream_values) {
+            values.push(stream_values);
+        }
+    }
+    let first = values.first()?;
+
+    // Values of each key, or None when a stream has no value for it
+    let columns: Vec<(&str, Option<Vec<&str>>)> = first
+        .iter()
+        .enumerate()
+        .map(|(index, (key, _))| (*key, values.iter().map(|stream| stream[index].1).collect()))
+        .collect();
+    let distinguishing = columns.iter().position(|(_, column)| {
+        column.as_ref().is_some_and(|column| {
+            column.iter().enumerate().all(|(index, value)| !column[..index].contains(value))
+        })
+    });
+
+    let mut filters = Vec::new();
+    for (index, (key, column)) in columns.iter().enumerate() {
+        let Some(column) = column else { continue };
+        let mut patterns: Vec<String> = Vec::new();
+        for value in column {
+            let pattern = escape_value(value);
+            if !patterns.contains(&pattern) {
+                patterns.push(pattern);
+            }
+        }
+        if patterns.len() == 1 {
+            filters.push(format!("{}=^{}$", key, patterns[0]));
+        } else if distinguishing.map_or(true, |distinguishing| distinguishing == index) {
+            // Without a distinguishing key every key has to alternate, which
+            // may match more than the picked streams
+            filters.push(format!("{}=^({})$", key, patterns.join("|")));
+        }
+    }
+
+    if streams.len() > 1 && !filters.is_empty() {
+        filters.push("for=all".to_string());
+    }
+    join_filters(filters)
+}
+
+fn push_filter(filters: &mut Vec<String>, key: &str, value: Option<&str>) {
+    if let Some(value) = value.map(str::trim).filter(|value| !value.is_empty()) {
+        filters.push(format!("{}={}", key, escape_value(value)));
+    }
+}
+
+// Filter values are regular expressions, so names like "English (AD)" are
+// escaped. Colons separate filters, so they are matched with a wildcard instead.
+fn escape_value(value: &str) -> String {
+    regex::escape(value).replace(':', ".")
+}
+
+fn join_filters(filters: Vec<String>) -> Option<String> {
+    if filters.is_empty() {
+        None
+    } else {
+        Some(filters.join(":"))
+    }
+}
+
+// Builds a stream ID from the parts that identify a stream in its manifest.
+// FNV-1a keeps it short and the same across runs and Rust versions.
+pub fn stable_id(kind: &str, parts: &[&str]) -> String {
+    let mut hash: u64 = 0xcbf29ce484222325;
+    for byte in parts.join("\u{1f}").bytes() {
+        hash ^= byte as u64;
+        hash = hash.wrapping_mul(0x100000001b3);
+    }
+    format!("{}-{:016x}", kind, hash)
+}
+
+// Streams picked before streams had IDs were stored as a " | " separated
+// description, e.g. in history entries and interrupted jobs
+pub trait FromDescription: Sized {
+    fn from_description(description: &str) -> Option<Self>;
+}
+
+impl FromDescription for VideoStream {
+    // "1920x1080 | 7968 | 60 | avc1.64002a", bitrate in Kbps
+    fn from_description(description: &str) -> Option<Self> {
+        let parts: Vec<&str> = description.split(" | ").map(str::trim).collect();
+        let [resolution, bitrate, fps, codecs, ..] = parts[..] else { return None };
+        Some(VideoStream {
+            id: stable_id("video", &parts),
+            resolution: non_empty(resolution),
+            bandwidth: bitrate.trim_end_matches("Kbps").trim().parse::<u64>().ok().map(|kbps| kbps * 1000),
+            frame_rate: fps.parse().ok(),
+            codecs: non_empty(codecs),
+            ..VideoStream::default()
+        })
+    }
+}
+
+impl FromDescription for AudioStream {
+    // "aud2 | English | en | 6CH"
+    fn from_description(description: &str) -> Option<Self> {
+        let parts: Vec<&str> = description.split(" | ").map(str::trim).collect();
+        let [group_id, name, language, channels, ..] = parts[..] else { return None };
+        Some(AudioStream {
+            id: stable_id("audio", &parts),
+            group_id: non_empty(group_id),
+            name: non_empty(name),
+            language: non_empty(language),
+            channels: non_empty(channels),
+            ..AudioStream::default()
+        })
+    }
+}
+
+impl FromDescription for SubtitleStream {
+    // "sub1 | English | en"
+    fn from_description(description: &str) -> Option<Self> {
+        let parts: Vec<&str> = description.split(" | ").map(str::trim).collect();
+        let [group_id, name, language, ..] = parts[..] else { return None };
+        Some(SubtitleStream {
+            id: stable_id("subtitle", &parts),
+            group_id: non_empty(group_id),
+            name: non_empty(name),
+            language: non_empty(language),
+            ..SubtitleStream::default()
+        })
+    }
+}
+
+// For `#[serde(deserialize_with)]` on stored selections: reads either a
+// stream or a legacy `{ "description": ... }` object
+pub fn deserialize_stored<'de, D, T>(deserializer: D) -> Result<Option<T>, D::Error>
+where
+    D: Deserializer<'de>,
+    T: Deserialize<'de> + FromDescription,
+{
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum Stored<T> {
+        Legacy { description: String },
+        Stream(T),
+    }
+
+    Ok(match Option::<Stored<T>>::deserialize(deserializer)? {
+        Some(Stored::Legacy { description }) => {
+            let stream = T::from_description(&description);
+            if stream.is_none() {
+                println!("Dropping unreadable stream selection: {}", description);
+            }
+            stream
+        }
+        Some(Stored::Stream(stream)) => Some(stream),
+        None => None,
+    })
+}
+
+// Like `deserialize_stored`, for selections that became lists: also reads a
+// single stream or legacy description, as written before
+pub fn deserialize_stored_list<'de, D, T>(deserializer: D) -> Result<Vec<T>, D::Error>
+where
+    D: Deserializer<'de>,
+    T: DeserializeOwned + FromDescription,
+{
+    let values = match Option::<Value>::deserialize(deserializer)? {
+        Some(Value::Array(values)) => values,
+        Some(value) => vec![value],
+        None => Vec::new(),
+    };
+    let mut streams = Vec::new();
+    for value in values {
+        if let Some(stream) = deserialize_stored(value).map_err(serde::de::Error::custom)? {
+            streams.push(stream);
+        }
+    }
+    Ok(streams)
+}
+
+pub fn non_empty(value: &str) -> Option<String> {
+    let value = value.trim();
+    if value.is_empty() {
+        None
+    } else {
+        Some(value.to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Deserialize)]
+    struct Selection {
+        #[serde(default, deserialize_with = "deserialize_stored")]
+        video: Option<VideoStream>,
+        #[serde(default, deserialize_with = "deserialize_stored")]
+        audio: Option<AudioStream>,
+    }
+
+    #[derive(Deserialize)]
+    struct ListSelection {
+        #[serde(default, deserialize_with = "deserialize_stored_list")]
+        audio: Vec<AudioStream>,
+    }
+
+    fn audio(group_id: &str, name: &str, language: &str) -> AudioStream {
+        AudioStream {
+            id: stable_id("audio", &[group_id, name, language]),
+            group_id: non_empty(group_id),
+            name: non_empty(name),
+            language: non_empty(language),
+            ..AudioStream::default()
+        }
+    }
+
+    #[test]
+    fn video_selector() {
+        let stream = VideoStream {
+            id: "video-1".to_string(),
+            resolution: Some("1920x1080".to_string()),
+            bandwidth: Some(7968000),
+            frame_rate: Some(29.97),
+            codecs: Some("avc1.64002a,mp4a.40.2".to_string()),
+            ..VideoStream::default()
+        };
+        assert_eq!(
+            stream.selector().as_deref(),
+            Some(r"res=1920x1080:frame=29\.97:codecs=avc1\.64002a,mp4a\.40\.2:bwMin=7967:bwMax=7969:for=best"),
+        );
+        assert_eq!(VideoStream::default().selector(), None);
+    }
+
+    #[test]
+    fn video_selector_prefers_identity() {
+        let representation = VideoStream {
+            group_id: Some("video=2962000".to_string()),
+            resolution: Some("1280x720".to_string()),
+            bandwidth: Some(2962000),
+            url: Some("https://example.com/v720.mp4".to_string()),
+            ..VideoStream::default()
+        };
+        assert_eq!(representation.selector().as_deref(), Some("id=^video=2962000$:bwMin=2961:bwMax=2963:for=best"));
+
+        let variant = VideoStream { group_id: None, bandwidth: None, ..representation };
+        assert_eq!(variant.selector().as_deref(), Some(r"url=https.//example\.com/v720\.mp4:for=best"));
+
+        // An unknown bitrate never turns into a window around zero
+        let unknown = VideoStream { resolution: Some("640x360".to_string()), bandwidth: Some(0), ..VideoStream::default() };
+        assert_eq!(unknown.selector().as_deref(), Some("res=640x360:for=best"));
+    }
+
+    #[test]
+    fn finds_video_by_identity() {
+        let video = |id: &str, url: Option<&str>, resolution: &str, bandwidth: u64| VideoStream {
+            id: id.to_string(),
+            url: url.map(String::from),
+            resolution: Some(resolution.to_string()),
+            bandwidth: Some(bandwidth),
+            ..VideoStream::default()
+        };
+        let streams = [
+            video("video-a", Some("https://example.com/1080.m3u8"), "1920x1080", 5000000),
+            video("video-b", Some("https://example.com/720.m3u8"), "1280x720", 2500000),
+        ];
+
+        assert_eq!(find_video(&streams, &streams[1]).unwrap().id, "video-b");
+        let moved = video("video-old", Some("https://example.com/720.m3u8"), "1280x720", 2400000);
+        assert_eq!(find_video(&streams, &moved).unwrap().id, "video-b");
+        let legacy = video("", None, "1920x1080", 5000000);
+        assert_eq!(find_video(&streams, &legacy).unwrap().id, "video-a");
+        let gone = video("video-c", Some("https://example.com/4k.m3u8"), "3840x2160", 16000000);
+        assert!(find_video(&streams, &gone).is_none());
+
+        let manifest = Some("https://example.com/manifest.mpd");
+        let streams = [video("video-a", manifest, "1920x1080", 5000000), video("video-b", manifest, "1280x720", 2500000)];
+        let moved = video("video-old", manifest, "1280x720", 2500000);
+        assert_eq!(find_video(&streams, &moved).unwrap().id, "video-b");
+        let gone = video("video-c", manifest, "3840x2160", 16000000);
+        assert!(find_video(&streams, &gone).is_none());
+    }
+
+    #[test]
+    fn single_stream_selectors_escape_values() {
+        let stream = audio("aud2", "English | AD (5.1): Director", "en");
+        assert_eq!(selector(&[stream]).as_deref(), Some(r"id=^aud2$:name=^English \| AD \(5\.1\). Director$:lang=^en$"));
+        let subtitle = SubtitleStream { language: Some("zh-Hans".to_string()), ..SubtitleStream::default() };
+        assert_eq!(selector(&[subtitle]).as_deref(), Some(r"lang=^zh\-Hans$"));
+        assert_eq!(selector::<AudioStream>(&[]), None);
+        assert_eq!(selector(&[AudioStream::default()]), None);
+    }
+
+    #[test]
+    fn multiple_streams_alternate_one_key() {
+        let streams = [audio("aud", "English", "en"), audio("aud", "日本語", "ja")];
+        assert_eq!(selector(&streams).as_deref(), Some("id=^aud$:name=^(English|日本語)$:for=all"));
+    }
+
+    // Applies a filter the way N_m3u8DL-RE does: every key's regex has to match
+    fn matches(filter: &str, stream: &AudioStream) -> bool {
+        filter.split(':').filter(|part| *part != "for=all").all(|part| {
+            let (key, pattern) = part.split_once('=').unwrap();
+            let value = stream.filter_values().into_iter().find(|(name, _)| *name == key).unwrap().1;
+            value.is_some_and(|value| regex::Regex::new(pattern).unwrap().is_match(value))
+        })
+    }
+
+    #[test]
+    fn multiple_streams_do_not_match_mixes_of_their_values() {
+        let with_channels = |channels: &str, stream: AudioStream| AudioStream { channels: Some(channels.to_string()), ..stream };
+        let english = with_channels("2", audio("aud", "English", "en"));
+        let japanese = with_channels("6", audio("aud", "日本語 5.1", "ja"));
+        let filter = selector(&[english.clone(), japanese.clone()]).unwrap();
+        assert_eq!(filter, r"id=^aud$:name=^(English|日本語 5\.1)$:for=all");
+        assert!(matches(&filter, &english) && matches(&filter, &japanese));
+        assert!(!matches(&filter, &with_channels("6", audio("aud", "English 5.1", "en"))));
+
+        let filter = selector(&[audio("aud", "English", "en")]).unwrap();
+        assert_eq!(filter, "id=^aud$:name=^English$:lang=^en$");
+        assert!(!matches(&filter, &audio("aud", "English", "en-GB")));
+    }
+
+    #[test]
+    fn keys_missing_on_any_stream_are_left_out() {
+        let streams = [audio("", "English", "en"), audio("aud2", "", "ja")];
+        assert_eq!(selector(&streams).as_deref(), Some("lang=^(en|ja)$:for=all"));
+        let subtitles = [
+            SubtitleStream { language: Some("zh-Hans".to_string()), ..SubtitleStream::default() },
+            SubtitleStream { language: Some("zh-Hant".to_string()), ..SubtitleStream::default() },
+            SubtitleStream { language: Some("zh-Hans".to_string()), ..SubtitleStream::default() },
+        ];
+        assert_eq!(selector(&subtitles).as_deref(), Some(r"lang=^(zh\-Hans|zh\-Hant)$:for=all"));
+    }
+
+    #[test]
+    fn stable_ids() {
+        assert_eq!(stable_id("video", &["a", "b"]), stable_id("video", &["a", "b"]));
+        assert_ne!(stable_id("video", &["a", "b"]), stable_id("video", &["ab"]));
+        assert_ne!(stable_id("video", &["a"]), stable_id("audio", &["a"]));
+    }
+
+    #[test]
+    fn reads_legacy_descriptions() {
+        let selection: Selection = serde_json::from_value(serde_json::json!({
+            "video": { "description": "1920x1080 | 7968 | 60 | avc1.64002a" },
+            "audio": { "description": "aud2 | English | en | 6CH" },
+        })).unwrap();
+        let video = selection.video.unwrap();
+        assert_eq!(video.resolution.as_deref(), Some("1920x1080"));
+        assert_eq!(video.bandwidth, Some(7968000));
+        assert_eq!(video.frame_rate, Some(60.0));
+        assert!(video.id.starts_with("video-"));
+        let audio = selection.audio.unwrap();
+        assert_eq!(audio.group_id.as_deref(), Some("aud2"));
+        assert_eq!(audio.channels.as_deref(), Some("6CH"));
+
+        let broken: Selection = serde_json::from_value(serde_json::json!({
+            "video": { "description": "1920x1080" },
+        })).unwrap();
+        assert!(broken.video.is_none() && broken.audio.is_none());
+    }
+
+    #[test]
+    fn reads_lists_single_streams_and_legacy_descriptions() {
+        let list: ListSelection = serde_json::from_value(serde_json::json!({
+            "audio": [audio("aud1", "English", "en"), { "description": "aud2 | 日本語 | ja | 2CH" }],
+        })).unwrap();
+        assert_eq!(list.audio.len(), 2);
+        assert_eq!(list.audio[1].language.as_deref(), Some("ja"));
+
+        let single: ListSelection = serde_json::from_value(serde_json::json!({
+            "audio": { "description": "aud2 | English | en | 6CH" },
+        })).unwrap();
+        assert_eq!(single.audio.len(), 1);
+
+        let empty: ListSelection = serde_json::from_value(serde_json::json!({ "audio": null })).unwrap();
+        assert!(empty.audio.is_empty());
+    }
+
+    #[test]
+    fn reads_current_streams() {
+        let stream = AudioStream {
+            id: "audio-1".to_string(),
+            language: Some("ja".to_string()),
+            ..AudioStream::default()
+        };
+        let selection: Selection = serde_json::from_value(serde_json::json!({
+            "audio": serde_json::to_value(&stream).unwrap(),
+        })).unwrap();
+        assert_eq!(selection.audio, Some(stream));
+    }
+}
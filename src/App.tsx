@@ -54,6 +54,19 @@ const App = () => {
     // Check for required binaries on startup
     invoke('check_required_binaries')
 
+    // Offer to resume downloads that were cut off when the app last closed
+    invoke<Array<{ id: number; options: { save_name: string } }>>('list_resumable_jobs')
+      .then(async (jobs) => {
+        for (const job of jobs) {
+          if (window.confirm(t('app.resumeInterrupted', { name: job.options.save_name }))) {
+            await invoke('resume_job', { jobId: job.id })
+          } else {
+            await invoke('discard_resumable_job', { jobId: job.id })
+          }
+        }
+      })
+      .catch(console.error)
+
     // Listen for missing binaries event
     const unlisten = listen('missing-binaries', (event) => {
       const missingBinaries = event.payload as string[]
@@ -1,52 +1,137 @@
-import React from 'react';
+import React, { useState, useEffect, useCallback } from 'react';
 import { useTranslation } from 'react-i18next';
+import { invoke } from '@tauri-apps/api/tauri';
 import '../styles/HistoryPage.css';
 
+interface Header {
+  key: string;
+  value: string;
+}
+
+// Stands in for header values that carry credentials, which are not saved
+const REDACTED = '<redacted>';
+
+interface HistoryEntry {
+  id: number;
+  url: string;
+  save_name: string;
+  headers: Header[];
+  status: string;
+  output_path: string | null;
+  started_at: number;
+  finished_at: number | null;
+}
+
+interface HistoryPageResult {
+  entries: HistoryEntry[];
+  total: number;
+  page: number;
+  page_size: number;
+}
+
 const HistoryPage: React.FC = () => {
   const { t } = useTranslation();
-  
-  // This is just a placeholder with mock data
-  const mockHistory = [
-    { id: 1, url: 'https://example.com/video1.m3u8', date: '2023-10-15', status: 'completed' },
-    { id: 2, url: 'https://example.com/video2.m3u8', date: '2023-10-14', status: 'failed' },
-    { id: 3, url: 'https://example.com/video3.m3u8', date: '2023-10-13', status: 'completed' },
-  ];
+  const [history, setHistory] = useState<HistoryEntry[]>([]);
+
+  const loadHistory = useCallback(async () => {
+    try {
+      const result = await invoke<HistoryPageResult>('list_history', { query: { page: 1, page_size: 100 } });
+      setHistory(result.entries);
+    } catch (error) {
+      console.error('Error loading history:', error);
+    }
+  }, []);
+
+  useEffect(() => {
+    loadHistory();
+  }, [loadHistory]);
+
+  const handleDelete = async (id: number) => {
+    if (!window.confirm(t('historyPage.confirmDelete'))) return;
+    try {
+      await invoke('delete_history_entry', { id });
+      await loadHistory();
+    } catch (error) {
+      console.error('Error deleting history entry:', error);
+    }
+  };
+
+  const handleRetry = async (entry: HistoryEntry) => {
+    // Credentials were not saved, so ask for them again
+    const headers: Header[] = [];
+    for (const header of entry.headers.filter(header => header.value === REDACTED)) {
+      const value = window.prompt(t('historyPage.reenterHeader', { name: header.key }), '');
+      if (value === null) return;
+      headers.push({ key: header.key, value });
+    }
+    try {
+      await invoke<number>('redownload', { historyId: entry.id, overrides: { headers } });
+      await loadHistory();
+    } catch (error) {
+      console.error('Error re-running download:', error);
+      window.alert(t('historyPage.retryFailed', { message: String(error) }));
+    }
+  };
+
+  const handleClear = async () => {
+    if (!window.confirm(t('historyPage.confirmClear'))) return;
+    try {
+      await invoke('clear_history');
+      await loadHistory();
+    } catch (error) {
+      console.error('Error clearing history:', error);
+    }
+  };
 
   return (
     <div className="history-page">
       <h1>{t('historyPage.title')}</h1>
       <div className="history-table-container">
-        <table className="history-table">
-          <thead>
-            <tr>
-              <th>{t('historyPage.table.id')}</th>
-              <th>{t('historyPage.table.url')}</th>
-              <th>{t('historyPage.table.date')}</th>
-              <th>{t('historyPage.table.status')}</th>
-              <th>{t('historyPage.table.actions')}</th>
-            </tr>
-          </thead>
-          <tbody>
-            {mockHistory.map((item) => (
-              <tr key={item.id}>
-                <td>{item.id}</td>
-                <td className="url-cell">{item.url}</td>
-                <td>{item.date}</td>
-                <td>
-                  <span className={`status-badge ${item.status}`}>
-                    {t(`historyPage.status.${item.status}`)}
-                  </span>
-                </td>
-                <td>
-                  <button className="action-button">{t('historyPage.actions.view')}</button>
-                </td>
-              </tr>
-            ))}
-          </tbody>
-        </table>
+        {history.length === 0 ? (
+          <p>{t('historyPage.empty')}</p>
+        ) : (
+          <>
+            <button className="action-button" onClick={handleClear}>{t('historyPage.clearHistory')}</button>
+            <table className="history-table">
+              <thead>
+                <tr>
+                  <th>{t('historyPage.table.id')}</th>
+                  <th>{t('historyPage.table.url')}</th>
+                  <th>{t('historyPage.table.date')}</th>
+                  <th>{t('historyPage.table.status')}</th>
+                  <th>{t('historyPage.table.actions')}</th>
+                </tr>
+              </thead>
+              <tbody>
+                {history.map((item) => (
+                  <tr key={item.id}>
+                    <td>{item.id}</td>
+                    <td className="url-cell" title={item.url}>{item.url}</td>
+                    <td>{new Date(item.started_at).toLocaleString()}</td>
+                    <td>
+                      <span className={`status-badge ${item.status}`}>
+                        {t(`historyPage.status.${item.status}`)}
+                      </span>
+                    </td>
+                    <td>
+                      {(item.status === 'failed' || item.status === 'cancelled') && (
+                        <button className="action-button" onClick={() => handleRetry(item)}>
+                          {t('historyPage.actions.retry')}
+                        </button>
+                      )}
+                      <button className="action-button" onClick={() => handleDelete(item.id)}>
+                        {t('historyPage.actions.delete')}
+                      </button>
+                    </td>
+                  </tr>
+                ))}
+              </tbody>
+            </table>
+          </>
+        )}
       </div>
     </div>
   );
 };
 
-export default HistoryPage; 
\ No newline at end of file
+export default HistoryPage;
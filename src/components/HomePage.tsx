@@ -1,35 +1,15 @@
-import React, { useState, useEffect, useCallback } from 'react';
+import React, { useState, useEffect, useCallback, useRef } from 'react';
 import { useTranslation } from 'react-i18next';
 import { invoke } from '@tauri-apps/api/tauri';
 import { listen } from '@tauri-apps/api/event';
 import '../styles/HomePage.css';
-import { MediaOptions } from '../types/tauri';
+import { DiscoveryError, LogChunk, LogLine, MediaOptions, ProfileList, Settings, StreamInfo } from '../types/tauri';
 
 interface Header {
   key: string;
   value: string;
 }
 
-interface StreamInfo {
-  video_streams: Array<{
-    resolution: string;
-    bitrate: string;
-    fps: string;
-    codec: string;
-  }>;
-  audio_streams: Array<{
-    id: string;
-    name: string;
-    language: string;
-    channels: string;
-  }>;
-  subtitle_streams: Array<{
-    id: string;
-    name: string;
-    language: string;
-  }>;
-}
-
 interface ProgressInfo {
   current: number;
   total: number;
@@ -38,12 +18,63 @@ interface ProgressInfo {
   total_size: string;
   speed: string;
   eta: string;
+  downloaded_bytes: number | null;
+  total_bytes: number | null;
+  bytes_per_second: number | null;
+  eta_seconds: number | null;
+}
+
+interface OverallProgress {
+  percentage: number;
+  downloaded_bytes: number;
+  total_bytes: number | null;
+  bytes_per_second: number;
+  eta_seconds: number | null;
 }
 
 interface DownloadProgress {
   video_progress: ProgressInfo;
   audio_progress: ProgressInfo;
   subtitle_progress: ProgressInfo;
+  overall: OverallProgress;
+}
+
+type DownloadPhase = 'parsing' | 'downloading' | 'merging' | 'decrypting' | 'muxing' | 'cleaning';
+
+interface PhaseChange {
+  phase: DownloadPhase;
+  started_at: number;
+}
+
+interface DownloadSummary {
+  total_bytes: number;
+  average_bytes_per_second: number;
+  elapsed_seconds: number;
+}
+
+// Sizes in the 1024-based units N_m3u8DL-RE uses
+const formatBytes = (bytes: number): string => {
+  const units = ['B', 'KB', 'MB', 'GB', 'TB'];
+  let value = bytes;
+  let unit = 0;
+  while (value >= 1024 && unit < units.length - 1) {
+    value /= 1024;
+    unit += 1;
+  }
+  return `${value.toFixed(unit === 0 ? 0 : 2)} ${units[unit]}`;
+};
+
+const formatDuration = (seconds: number): string => {
+  const total = Math.round(seconds);
+  const hours = Math.floor(total / 3600);
+  const minutes = Math.floor((total % 3600) / 60);
+  const rest = total % 60;
+  return [hours, minutes, rest].map(part => String(part).padStart(2, '0')).join(':');
+};
+
+interface JobEvent<T> {
+  job_id: number;
+  data: T;
 }
 
 const HomePage: React.FC = () => {
@@ -58,22 +89,44 @@ const HomePage: React.FC = () => {
   const [urlError, setUrlError] = useState<string>('');
   const [saveName, setSaveName] = useState('');
   const [videoQuality, setVideoQuality] = useState('');
-  const [audioStream, setAudioStream] = useState('');
-  const [subtitles, setSubtitles] = useState('');
+  const [audioStreams, setAudioStreams] = useState<string[]>([]);
+  const [subtitles, setSubtitles] = useState<string[]>([]);
   const [autoMerge, setAutoMerge] = useState(true);
   const [fastStart, setFastStart] = useState(false);
   const [audioOnly, setAudioOnly] = useState(false);
+  // Empty means the active settings profile
+  const [profile, setProfile] = useState('');
+  const [profiles, setProfiles] = useState<ProfileList | null>(null);
 
   const [videoOptions, setVideoOptions] = useState<MediaOptions[]>([]);
   const [audioOptions, setAudioOptions] = useState<MediaOptions[]>([]);
   const [subtitleOptions, setSubtitleOptions] = useState<MediaOptions[]>([]);
+  const [streamInfo, setStreamInfo] = useState<StreamInfo | null>(null);
   const [isLoading, setIsLoading] = useState(false);
+  const [loadError, setLoadError] = useState<DiscoveryError | null>(null);
   const [downloadProgress, setDownloadProgress] = useState<DownloadProgress | null>(null);
+  const [downloadSummary, setDownloadSummary] = useState<DownloadSummary | null>(null);
+  const [downloadPhase, setDownloadPhase] = useState<PhaseChange | null>(null);
   const [downloadError, setDownloadError] = useState<string>('');
   const [isDownloading, setIsDownloading] = useState(false);
+  // The job waits for the queue to be started because auto-start is off
+  const [waitingForQueue, setWaitingForQueue] = useState(false);
   const [downloadStatus, setDownloadStatus] = useState<'idle' | 'downloading' | 'error' | 'complete'>('idle');
   const [errorMessage, setErrorMessage] = useState<string>('');
   const [notificationDismissed, setNotificationDismissed] = useState(false);
+  const currentJobId = useRef<number | null>(null);
+  const [showLog, setShowLog] = useState(false);
+  const [logLines, setLogLines] = useState<LogLine[]>([]);
+  const [logPath, setLogPath] = useState<string | null>(null);
+  const logOffset = useRef(0);
+  // Stream probe started by the last load, so it can be cancelled
+  const currentProbeId = useRef<number | null>(null);
+
+  useEffect(() => {
+    invoke<ProfileList>('list_profiles')
+      .then(setProfiles)
+      .catch((error) => console.error('Failed to load settings profiles:', error));
+  }, []);
 
   const handleAddHeader = () => {
     setHeaders([...headers, { key: '', value: '' }]);
@@ -95,12 +148,11 @@ const HomePage: React.FC = () => {
       const pathname = urlObj.pathname.toLowerCase();
       const searchParams = urlObj.searchParams.toString().toLowerCase();
       
-      // Check if URL ends with .m3u8 or contains m3u8 in the path/query
-      const isM3u8 = pathname.endsWith('.m3u8') || 
-                    pathname.includes('m3u8') || 
-                    searchParams.includes('m3u8');
+      // Check if the path or query points at an HLS, DASH or Smooth Streaming manifest
+      const isManifest = ['m3u8', 'mpd', 'ism'].some(extension =>
+        pathname.includes(extension) || searchParams.includes(extension));
       
-      if (!isM3u8) {
+      if (!isManifest) {
         setUrlError(t('homePage.invalidM3u8Url'));
         return false;
       }
@@ -137,35 +189,57 @@ const HomePage: React.FC = () => {
     setVideoOptions([]);
     setAudioOptions([]);
     setSubtitleOptions([]);
+    setStreamInfo(null);
     setVideoQuality('');
-    setAudioStream('');
-    setSubtitles('');
+    setAudioStreams([]);
+    setSubtitles([]);
+    setLoadError(null);
     setIsLoading(true);
 
+    const unlistenProbe = await listen<{ probe_id: number; url: string }>('probe-started', (event) => {
+      if (event.payload.url === url) {
+        currentProbeId.current = event.payload.probe_id;
+      }
+    });
+
     try {
       // Set up event listener for m3u8 options
       const unlisten = await listen<StreamInfo>('m3u8-options', (event) => {
         const streamInfo = event.payload;
         console.log('Received stream info:', streamInfo);
         
-        // Convert video streams to MediaOptions
+        // Convert streams to MediaOptions, the description is only for display
+        const describe = (parts: Array<string | null | undefined | false>) =>
+          parts.filter(Boolean).join(' | ');
+
         const videoOptions = streamInfo.video_streams.map(stream => ({
-          id: `${stream.resolution} | ${stream.bitrate} | ${stream.fps} | ${stream.codec}`,
-          description: `${stream.resolution} | ${stream.bitrate} | ${stream.fps} | ${stream.codec}`
+          id: stream.id,
+          description: describe([
+            stream.resolution,
+            stream.bandwidth != null && `${Math.round(stream.bandwidth / 1000)} Kbps`,
+            stream.frame_rate != null && `${stream.frame_rate} fps`,
+            stream.codecs,
+            stream.encrypted && 'DRM',
+          ])
         }));
-        
-        // Convert audio streams to MediaOptions
+
         const audioOptions = streamInfo.audio_streams.map(stream => ({
-          id: `${stream.id} | ${stream.name} | ${stream.language} | ${stream.channels}`,
-          description: `${stream.id} | ${stream.name} | ${stream.language} | ${stream.channels}`
+          id: stream.id,
+          description: describe([
+            stream.group_id,
+            stream.name,
+            stream.language,
+            stream.channels && `${stream.channels}CH`,
+            stream.encrypted && 'DRM',
+          ])
         }));
-        
-        // Convert subtitle streams to MediaOptions
+
         const subtitleOptions = streamInfo.subtitle_streams.map(stream => ({
-          id: `${stream.id} | ${stream.name} | ${stream.language}`,
-          description: `${stream.id} | ${stream.name} | ${stream.language}`
+          id: stream.id,
+          description: describe([stream.group_id, stream.name, stream.language])
         }));
         
+        setStreamInfo(streamInfo);
         setVideoOptions(videoOptions);
         setAudioOptions(audioOptions);
         setSubtitleOptions(subtitleOptions);
@@ -175,13 +249,33 @@ const HomePage: React.FC = () => {
       // Call IPC to load options
       await invoke('load_m3u8_options', {
         url,
-        headers: headers.filter(header => header.key && header.value)
+        headers: headers.filter(header => header.key && header.value),
+        profile: profile || undefined
       });
 
     } catch (error) {
       console.error('Error loading options:', error);
       setIsLoading(false);
-      // TODO: Show an error message to the user
+      const discoveryError = error as DiscoveryError;
+      if (discoveryError?.kind !== 'cancelled') {
+        setLoadError(discoveryError?.kind ? discoveryError : {
+          kind: 'other',
+          message: String(error),
+          status: null,
+          output: null
+        });
+      }
+    } finally {
+      unlistenProbe();
+      currentProbeId.current = null;
+    }
+  };
+
+  const handleCancelLoad = async () => {
+    try {
+      await invoke('cancel_probe', { probeId: currentProbeId.current ?? undefined });
+    } catch (error) {
+      console.error('Failed to cancel loading options:', error);
     }
   };
 
@@ -191,18 +285,31 @@ const HomePage: React.FC = () => {
   }, []);
 
   // Handle download progress updates
-  const handleProgressUpdate = useCallback((event: any) => {
+  const handleProgressUpdate = useCallback((event: { payload: JobEvent<DownloadProgress> }) => {
+    if (event.payload.job_id !== currentJobId.current) return;
     console.log('Received progress update:', event.payload);
-    setDownloadProgress(event.payload);
+    setDownloadProgress(event.payload.data);
+  }, []);
+
+  const handleDownloadPhase = useCallback((event: { payload: JobEvent<PhaseChange> }) => {
+    if (event.payload.job_id !== currentJobId.current) return;
+    setDownloadPhase(event.payload.data);
+  }, []);
+
+  // Sent right before download-complete
+  const handleDownloadSummary = useCallback((event: { payload: JobEvent<DownloadSummary> }) => {
+    if (event.payload.job_id !== currentJobId.current) return;
+    setDownloadSummary(event.payload.data);
   }, []);
 
   // Handle download completion
-  const handleDownloadComplete = useCallback((event: any) => {
+  const handleDownloadComplete = useCallback((event: { payload: JobEvent<number | null> }) => {
+    if (event.payload.job_id !== currentJobId.current) return;
     console.log('Download completed:', event.payload);
     setIsDownloading(false);
-    setDownloadStatus(event.payload === 0 ? 'complete' : 'error');
+    setDownloadStatus(event.payload.data === 0 ? 'complete' : 'error');
     setNotificationDismissed(false);
-    if (event.payload === 0) {
+    if (event.payload.data === 0) {
       setDownloadProgress(null);
     } else {
       setErrorMessage(t('homePage.downloadFailed'));
@@ -210,11 +317,12 @@ const HomePage: React.FC = () => {
   }, [t]);
 
   // Handle download errors
-  const handleDownloadError = useCallback((event: any) => {
+  const handleDownloadError = useCallback((event: { payload: JobEvent<string> }) => {
+    if (event.payload.job_id !== currentJobId.current) return;
     console.log('Download error:', event.payload);
     setIsDownloading(false);
     setDownloadStatus('error');
-    setErrorMessage(event.payload as string);
+    setErrorMessage(event.payload.data);
   }, []);
 
   // Set up event listeners
@@ -223,57 +331,68 @@ const HomePage: React.FC = () => {
       const listeners: (() => void)[] = [];
       
       // Set up progress listener
-      listen<DownloadProgress>('download-progress', handleProgressUpdate)
+      listen<JobEvent<DownloadProgress>>('download-progress', handleProgressUpdate)
+        .then(unlisten => listeners.push(unlisten))
+        .catch(console.error);
+
+      listen<JobEvent<PhaseChange>>('download-phase', handleDownloadPhase)
+        .then(unlisten => listeners.push(unlisten))
+        .catch(console.error);
+
+      listen<JobEvent<DownloadSummary>>('download-summary', handleDownloadSummary)
         .then(unlisten => listeners.push(unlisten))
         .catch(console.error);
 
       // Set up completion listener
-      listen('download-complete', handleDownloadComplete)
+      listen<JobEvent<number | null>>('download-complete', handleDownloadComplete)
         .then(unlisten => listeners.push(unlisten))
         .catch(console.error);
 
       // Set up error listener
-      listen('download-error', handleDownloadError)
+      listen<JobEvent<string>>('download-error', handleDownloadError)
         .then(unlisten => listeners.push(unlisten))
         .catch(console.error);
 
       // Cleanup function
       return () => cleanupListeners(listeners);
     }
-  }, [isDownloading, handleProgressUpdate, handleDownloadComplete, handleDownloadError, cleanupListeners]);
+  }, [isDownloading, handleProgressUpdate, handleDownloadPhase, handleDownloadSummary, handleDownloadComplete, handleDownloadError, cleanupListeners]);
 
-  // Improved progress calculation with null checks
+  // The backend weighs the streams by their size
   const calculateOverallProgress = useCallback((progress: DownloadProgress | null): number => {
     if (!progress) return 0;
     
-    const videoProgress = progress.video_progress?.total > 0 
-      ? (progress.video_progress.current / progress.video_progress.total) * 100 
-      : progress.video_progress?.percentage || 0;
-    
-    const audioProgress = progress.audio_progress?.total > 0
-      ? (progress.audio_progress.current / progress.audio_progress.total) * 100
-      : progress.audio_progress?.percentage || 0;
-    
-    const subtitleProgress = progress.subtitle_progress?.total > 0
-      ? (progress.subtitle_progress.current / progress.subtitle_progress.total) * 100
-      : progress.subtitle_progress?.percentage || 0;
-    
     if (audioOnly) {
-      return audioProgress;
+      return progress.audio_progress?.percentage || 0;
     }
     
-    // Use weighted average for multi-stream downloads
-    const weights = {
-      video: 0.7,
-      audio: 0.2,
-      subtitle: 0.1
-    };
-    
-    return (videoProgress * weights.video) + 
-           (audioProgress * weights.audio) + 
-           (subtitleProgress * weights.subtitle);
+    return progress.overall?.percentage || 0;
   }, [audioOnly]);
 
+  // Polls the job's log while it is shown, once more after the download ends
+  useEffect(() => {
+    const jobId = currentJobId.current;
+    if (!showLog || jobId === null) return;
+
+    const fetchLog = () => {
+      invoke<LogChunk>('get_job_log', { jobId, offset: logOffset.current })
+        .then(chunk => {
+          logOffset.current = chunk.next_offset;
+          setLogPath(chunk.path);
+          if (chunk.lines.length > 0) {
+            // Keep about as much as the backend does
+            setLogLines(lines => [...lines, ...chunk.lines].slice(-5000));
+          }
+        })
+        .catch(console.error);
+    };
+
+    fetchLog();
+    if (!isDownloading) return;
+    const timer = setInterval(fetchLog, 1000);
+    return () => clearInterval(timer);
+  }, [showLog, isDownloading]);
+
   // Improved progress info retrieval with null checks
   const getProgressInfo = useCallback((progress: DownloadProgress | null): ProgressInfo | null => {
     if (!progress) return null;
@@ -290,44 +409,57 @@ const HomePage: React.FC = () => {
   }, [audioOnly]);
 
   // Add a function to format progress info
-  const formatProgressInfo = (info: ProgressInfo | null): { downloaded: string; speed: string; eta: string } => {
-    if (!info) {
-      console.log('No progress info available');
+  const formatProgressInfo = (progress: DownloadProgress | null, info: ProgressInfo | null): { downloaded: string; speed: string; eta: string } => {
+    if (!progress || !info) {
       return { downloaded: '0.00%', speed: '0 MB/s', eta: '--:--' };
     }
     
-    // Calculate percentage if we have current/total
-    const percentage = info.total > 0 
-      ? ((info.current / info.total) * 100).toFixed(1) + '%'
-      : info.downloaded || '0.00%';
+    const overall = audioOnly
+      ? {
+          downloaded_bytes: info.downloaded_bytes ?? 0,
+          total_bytes: info.total_bytes,
+          bytes_per_second: info.bytes_per_second ?? 0,
+          eta_seconds: info.eta_seconds,
+        }
+      : progress.overall;
     
-    const formatted = {
-      downloaded: percentage,
-      speed: info.speed || '0 MB/s',
-      eta: info.eta || '--:--'
-    };
-    
-    console.log('Formatted progress info:', {
-      raw: info,
-      formatted
-    });
+    const downloaded = overall.total_bytes
+      ? `${formatBytes(overall.downloaded_bytes)} / ${formatBytes(overall.total_bytes)}`
+      : overall.downloaded_bytes > 0
+        ? formatBytes(overall.downloaded_bytes)
+        : info.downloaded || '0.00%';
     
-    return formatted;
+    return {
+      downloaded,
+      speed: overall.bytes_per_second > 0 ? `${formatBytes(overall.bytes_per_second)}/s` : info.speed || '0 MB/s',
+      eta: overall.eta_seconds != null ? formatDuration(overall.eta_seconds) : info.eta || '--:--'
+    };
   };
 
+  const selectedValues = (select: HTMLSelectElement) =>
+    Array.from(select.selectedOptions, option => option.value);
+
   const isDownloadEnabled = () => {
     // Check if save name is provided
     if (!saveName.trim()) return false;
 
     // If audioOnly is true, only check audio stream
     if (audioOnly) {
-      return audioStream !== '';
+      return audioStreams.length > 0;
     }
 
     // Otherwise, require video quality
     return videoQuality !== '';
   };
 
+  const handleStartQueue = async () => {
+    try {
+      await invoke('start_queue');
+    } catch (error) {
+      console.error('Failed to start the queue:', error);
+    }
+  };
+
   const handleDownload = async () => {
     if (!isDownloadEnabled()) return;
 
@@ -335,17 +467,23 @@ const HomePage: React.FC = () => {
     setIsDownloading(true);
     setDownloadError('');
     setDownloadProgress(null);
+    setDownloadSummary(null);
+    setDownloadPhase(null);
+    setWaitingForQueue(false);
+    setLogLines([]);
+    setLogPath(null);
+    logOffset.current = 0;
 
     try {
       // Find the selected stream objects
-      const selectedVideo = videoOptions.find(opt => opt.id === videoQuality);
-      const selectedAudio = audioOptions.find(opt => opt.id === audioStream);
-      const selectedSubtitle = subtitleOptions.find(opt => opt.id === subtitles);
+      const selectedVideo = streamInfo?.video_streams.find(stream => stream.id === videoQuality);
+      const selectedAudio = streamInfo?.audio_streams.filter(stream => audioStreams.includes(stream.id)) ?? [];
+      const selectedSubtitles = streamInfo?.subtitle_streams.filter(stream => subtitles.includes(stream.id)) ?? [];
 
       console.log('Selected streams:', {
         video: selectedVideo,
         audio: selectedAudio,
-        subtitle: selectedSubtitle
+        subtitles: selectedSubtitles
       });
 
       // Gather all options
@@ -354,17 +492,20 @@ const HomePage: React.FC = () => {
         save_name: saveName,
         headers: headers.filter(header => header.key && header.value),
         video_quality: selectedVideo,
-        audio_stream: selectedAudio,
-        subtitles: selectedSubtitle,
+        audio_streams: selectedAudio,
+        subtitles: selectedSubtitles,
         auto_merge: autoMerge,
         audio_only: audioOnly,
+        profile: profile || undefined,
       };
 
       console.log('Download options:', downloadOptions);
 
       // Start download using Tauri invoke AFTER setting up listeners
       console.log('Starting download with Tauri invoke...');
-      await invoke('start_download', { options: downloadOptions });
+      currentJobId.current = await invoke<number>('start_download', { options: downloadOptions });
+      const settings = await invoke<Settings>('get_settings');
+      setWaitingForQueue(settings.auto_start === false);
 
     } catch (error) {
       console.error('Download error:', error);
@@ -405,13 +546,28 @@ const HomePage: React.FC = () => {
                 onChange={handleUrlChange}
               />
               {urlError && <div className="url-error">{urlError}</div>}
+              {loadError && (
+                <div className="url-error">
+                  {t(`homePage.discoveryErrors.${loadError.kind}`, { status: loadError.status })}
+                  {' '}{loadError.message}
+                  {loadError.output && (
+                    <details>
+                      <summary>{t('homePage.discoveryErrors.details')}</summary>
+                      <pre>{loadError.output}</pre>
+                    </details>
+                  )}
+                </div>
+              )}
+              {streamInfo?.drm_only && (
+                <div className="url-error">{t('homePage.drmOnlyWarning')}</div>
+              )}
             </div>
             <button 
               className="load-options-button" 
-              onClick={handleLoadOptions}
+              onClick={isLoading ? handleCancelLoad : handleLoadOptions}
               disabled={!!urlError}
             >
-              {t('homePage.loadOptions')}
+              {isLoading ? t('homePage.cancelLoadOptions') : t('homePage.loadOptions')}
             </button>
           </div>
 
@@ -436,6 +592,25 @@ const HomePage: React.FC = () => {
                   {t('homePage.editHeaders')}
                 </button>
               </div>
+              {profiles && profiles.profiles.length > 1 && (
+                <div className="option-item">
+                  <label>{t('homePage.settingsProfile')}</label>
+                  <select
+                    className="option-input"
+                    value={profile}
+                    onChange={(e) => setProfile(e.target.value)}
+                  >
+                    <option value="">
+                      {t('homePage.activeProfile', { name: profiles.active_profile })}
+                    </option>
+                    {profiles.profiles
+                      .filter((name) => name !== profiles.active_profile)
+                      .map((name) => (
+                        <option key={name} value={name}>{name}</option>
+                      ))}
+                  </select>
+                </div>
+              )}
             </div>
 
             <div className="option-group">
@@ -465,9 +640,11 @@ const HomePage: React.FC = () => {
               <div className="option-item">
                 <label>{t('homePage.audioStream')}</label>
                 <select 
+                  multiple
                   className={`option-select ${audioOptions.length === 0 ? 'disabled' : ''}`}
-                  value={audioStream}
-                  onChange={(e) => setAudioStream(e.target.value)}
+                  value={audioStreams}
+                  title={t('homePage.selectAudio')}
+                  onChange={(e) => setAudioStreams(selectedValues(e.target))}
                   disabled={audioOptions.length === 0}
                 >
                   {isLoading ? (
@@ -475,21 +652,20 @@ const HomePage: React.FC = () => {
                   ) : audioOptions.length === 0 ? (
                     <option value="">{t('homePage.audioUnavailable')}</option>
                   ) : (
-                    <>
-                      <option value="">{t('homePage.selectAudio')}</option>
-                      {audioOptions.map(option => (
-                        <option key={option.id} value={option.id}>{option.description}</option>
-                      ))}
-                    </>
+                    audioOptions.map(option => (
+                      <option key={option.id} value={option.id}>{option.description}</option>
+                    ))
                   )}
                 </select>
               </div>
               <div className="option-item">
                 <label>{t('homePage.subtitles')}</label>
                 <select 
+                  multiple
                   className={`option-select ${subtitleOptions.length === 0 ? 'disabled' : ''}`}
                   value={subtitles}
-                  onChange={(e) => setSubtitles(e.target.value)}
+                  title={t('homePage.selectSubtitles')}
+                  onChange={(e) => setSubtitles(selectedValues(e.target))}
                   disabled={subtitleOptions.length === 0}
                 >
                   {isLoading ? (
@@ -497,12 +673,9 @@ const HomePage: React.FC = () => {
                   ) : subtitleOptions.length === 0 ? (
                     <option value="">{t('homePage.subtitlesUnavailable')}</option>
                   ) : (
-                    <>
-                      <option value="">{t('homePage.selectSubtitles')}</option>
-                      {subtitleOptions.map(option => (
-                        <option key={option.id} value={option.id}>{option.description}</option>
-                      ))}
-                    </>
+                    subtitleOptions.map(option => (
+                      <option key={option.id} value={option.id}>{option.description}</option>
+                    ))
                   )}
                 </select>
               </div>
@@ -547,11 +720,28 @@ const HomePage: React.FC = () => {
           </button>
 
           <div className="download-status">
+            {isDownloading && waitingForQueue && !downloadPhase && (
+              <div className="download-progress">
+                <div className="progress-phase">{t('homePage.waitingForQueue')}</div>
+                <button className="download-button" onClick={handleStartQueue}>
+                  {t('homePage.startQueue')}
+                </button>
+              </div>
+            )}
+            {isDownloading && !downloadProgress && downloadPhase && (
+              <div className="download-progress">
+                <div className="progress-phase">{t(`homePage.phases.${downloadPhase.phase}`)}</div>
+              </div>
+            )}
             {isDownloading && downloadProgress && (
               <div className="download-progress">
                 <div className="progress-section">
                   <div className="progress-header">
-                    <span>{audioOnly ? t('homePage.audio') : t('homePage.download')}</span>
+                    <span>
+                      {downloadPhase && downloadPhase.phase !== 'downloading'
+                        ? t(`homePage.phases.${downloadPhase.phase}`)
+                        : audioOnly ? t('homePage.audio') : t('homePage.download')}
+                    </span>
                     <span>{calculateOverallProgress(downloadProgress).toFixed(1)}%</span>
                   </div>
                   <div className="progress-bar">
@@ -563,7 +753,7 @@ const HomePage: React.FC = () => {
                   <div className="progress-details">
                     {(() => {
                       const info = getProgressInfo(downloadProgress);
-                      const formatted = formatProgressInfo(info);
+                      const formatted = formatProgressInfo(downloadProgress, info);
                       return (
                         <>
                           <span>{formatted.downloaded}</span>
@@ -597,9 +787,29 @@ const HomePage: React.FC = () => {
                 <div className="notification-content">
                   <span className="notification-icon">✓</span>
                   <span className="notification-text">{t('homePage.downloadComplete')}</span>
+                  {downloadSummary && (
+                    <span className="notification-details">
+                      {t('homePage.downloadSummary', {
+                        size: formatBytes(downloadSummary.total_bytes),
+                        elapsed: formatDuration(downloadSummary.elapsed_seconds),
+                        speed: `${formatBytes(downloadSummary.average_bytes_per_second)}/s`,
+                      })}
+                    </span>
+                  )}
                 </div>
               </div>
             )}
+            {downloadStatus !== 'idle' && currentJobId.current !== null && (
+              <details className="download-log" onToggle={e => setShowLog(e.currentTarget.open)}>
+                <summary>{t('homePage.log.show')}</summary>
+                {logPath && <div className="download-log-path">{t('homePage.log.file', { path: logPath })}</div>}
+                <pre>
+                  {logLines.length > 0
+                    ? logLines.map(line => line.text).join('\n')
+                    : t('homePage.log.empty')}
+                </pre>
+              </details>
+            )}
           </div>
         </div>
       </div>
@@ -3,12 +3,13 @@ import { useTranslation } from 'react-i18next';
 import { invoke } from '@tauri-apps/api/tauri';
 import { open } from '@tauri-apps/api/dialog';
 import '../styles/SettingsPage.css';
-import { Settings } from '../types/tauri';
+import { ProfileList, Settings } from '../types/tauri';
 
 const SettingsPage: React.FC = () => {
   const { t } = useTranslation();
   const [settings, setSettings] = useState<Settings>({});
   const [isDirty, setIsDirty] = useState(false);
+  const [profiles, setProfiles] = useState<ProfileList>({ active_profile: '', profiles: [] });
   const [feedback, setFeedback] = useState<{
     message: string;
     type: 'success' | 'error' | null;
@@ -29,6 +30,7 @@ const SettingsPage: React.FC = () => {
     try {
       const savedSettings = await invoke<Settings>('get_settings');
       setSettings(savedSettings);
+      setProfiles(await invoke<ProfileList>('list_profiles'));
       showFeedback(t('settingsPage.feedback.settingsLoaded'), 'success');
     } catch (error) {
       showFeedback(t('settingsPage.feedback.loadError'), 'error');
@@ -46,7 +48,7 @@ const SettingsPage: React.FC = () => {
   };
 
   const handleFormatChange = (e: React.ChangeEvent<HTMLSelectElement>) => {
-    setSettings({ ...settings, default_format: e.target.value });
+    setSettings({ ...settings, default_format: e.target.value as Settings['default_format'] });
     setIsDirty(true);
   };
 
@@ -56,7 +58,12 @@ const SettingsPage: React.FC = () => {
   };
 
   const handleLogLevelChange = (e: React.ChangeEvent<HTMLSelectElement>) => {
-    setSettings({ ...settings, log_level: e.target.value });
+    setSettings({ ...settings, log_level: e.target.value as Settings['log_level'] });
+    setIsDirty(true);
+  };
+
+  const handleDiscoveryModeChange = (e: React.ChangeEvent<HTMLSelectElement>) => {
+    setSettings({ ...settings, discovery_mode: e.target.value as Settings['discovery_mode'] });
     setIsDirty(true);
   };
 
@@ -83,7 +90,56 @@ const SettingsPage: React.FC = () => {
       setIsDirty(false);
       showFeedback(t('settingsPage.feedback.saved'), 'success');
     } catch (error) {
-      showFeedback(t('settingsPage.feedback.saveError'), 'error');
+      // Validation errors from the backend name the offending fields
+      showFeedback(t('settingsPage.feedback.error', { message: error }), 'error');
+    }
+  };
+
+  const handleSwitchProfile = async (name: string) => {
+    if (isDirty && !window.confirm(t('settingsPage.profiles.discardChanges'))) {
+      return;
+    }
+    try {
+      setSettings(await invoke<Settings>('switch_profile', { name }));
+      setProfiles({ ...profiles, active_profile: name });
+      setIsDirty(false);
+      showFeedback(t('settingsPage.profiles.switched', { name }), 'success');
+    } catch (error) {
+      showFeedback(t('settingsPage.feedback.error', { message: error }), 'error');
+    }
+  };
+
+  // New profiles start from the defaults, clones from the active profile
+  const handleCreateProfile = async (clone: boolean) => {
+    const name = window.prompt(t(clone ? 'settingsPage.profiles.clonePrompt' : 'settingsPage.profiles.newPrompt'));
+    if (!name) {
+      return;
+    }
+    try {
+      const updated = clone
+        ? await invoke<ProfileList>('clone_profile', { source: profiles.active_profile, name })
+        : await invoke<ProfileList>('create_profile', { name });
+      setProfiles(updated);
+      showFeedback(t('settingsPage.profiles.created', { name: name.trim() }), 'success');
+    } catch (error) {
+      showFeedback(t('settingsPage.feedback.error', { message: error }), 'error');
+    }
+  };
+
+  // The active profile cannot be deleted, so switch away from it first
+  const handleDeleteProfile = async () => {
+    const name = profiles.active_profile;
+    const fallback = profiles.profiles.find((profile) => profile !== name);
+    if (!fallback || !window.confirm(t('settingsPage.profiles.deleteConfirm', { name }))) {
+      return;
+    }
+    try {
+      setSettings(await invoke<Settings>('switch_profile', { name: fallback }));
+      setProfiles(await invoke<ProfileList>('delete_profile', { name }));
+      setIsDirty(false);
+      showFeedback(t('settingsPage.profiles.deleted', { name }), 'success');
+    } catch (error) {
+      showFeedback(t('settingsPage.feedback.error', { message: error }), 'error');
     }
   };
 
@@ -108,6 +164,34 @@ const SettingsPage: React.FC = () => {
         </div>
       )}
       
+      <div className="settings-section">
+        <h2>{t('settingsPage.profiles.title')}</h2>
+        <div className="setting-item">
+          <label data-tooltip={t('settingsPage.profiles.tooltip')}>
+            {t('settingsPage.profiles.active')}
+          </label>
+          <div className="path-input">
+            <select
+              value={profiles.active_profile}
+              onChange={(e) => handleSwitchProfile(e.target.value)}
+            >
+              {profiles.profiles.map((name) => (
+                <option key={name} value={name}>{name}</option>
+              ))}
+            </select>
+            <button onClick={() => handleCreateProfile(false)}>
+              {t('settingsPage.profiles.new')}
+            </button>
+            <button onClick={() => handleCreateProfile(true)}>
+              {t('settingsPage.profiles.clone')}
+            </button>
+            <button onClick={handleDeleteProfile} disabled={profiles.profiles.length < 2}>
+              {t('settingsPage.profiles.delete')}
+            </button>
+          </div>
+        </div>
+      </div>
+
       <div className="settings-grid">
         {/* General Settings */}
         <div className="settings-section">
@@ -166,7 +250,7 @@ const SettingsPage: React.FC = () => {
                 type="number"
                 min="1"
                 max="10"
-                value={settings.download_retry_count || '3'}
+                value={settings.download_retry_count || 3}
                 onChange={handleNumberChange('download_retry_count')}
               />
             </div>
@@ -221,7 +305,7 @@ const SettingsPage: React.FC = () => {
               <input
                 type="checkbox"
                 id="autoStart"
-                checked={settings.auto_start || false}
+                checked={settings.auto_start ?? true}
                 onChange={handleCheckboxChange('auto_start')}
               />
               <label htmlFor="autoStart" data-tooltip={t('settingsPage.generalSettings.autoStart.tooltip')}>
@@ -248,6 +332,16 @@ const SettingsPage: React.FC = () => {
               </select>
             </div>
 
+            <div className="setting-item">
+              <label data-tooltip={t('settingsPage.advancedSettings.discoveryMode.tooltip')}>
+                {t('settingsPage.advancedSettings.discoveryMode.label')}
+              </label>
+              <select value={settings.discovery_mode || 'native'} onChange={handleDiscoveryModeChange}>
+                <option value="native">{t('settingsPage.advancedSettings.discoveryMode.native')}</option>
+                <option value="meta_json">{t('settingsPage.advancedSettings.discoveryMode.metaJson')}</option>
+              </select>
+            </div>
+
             <div className="setting-item">
               <label data-tooltip={t('settingsPage.advancedSettings.concurrentDownloads.tooltip')}>
                 {t('settingsPage.advancedSettings.concurrentDownloads.label')}
@@ -256,7 +350,7 @@ const SettingsPage: React.FC = () => {
                 type="number"
                 min="1"
                 max="10"
-                value={settings.concurrent_downloads || '1'}
+                value={settings.concurrent_downloads || 1}
                 onChange={handleNumberChange('concurrent_downloads')}
               />
             </div>
@@ -269,11 +363,24 @@ const SettingsPage: React.FC = () => {
                 type="number"
                 min="1"
                 max="3600"
-                value={settings.timeout || '30'}
+                value={settings.timeout || 30}
                 onChange={handleNumberChange('timeout')}
               />
             </div>
 
+            <div className="setting-item">
+              <label data-tooltip={t('settingsPage.advancedSettings.maxProgressRate.tooltip')}>
+                {t('settingsPage.advancedSettings.maxProgressRate.label')}
+              </label>
+              <input
+                type="number"
+                min="1"
+                max="60"
+                value={settings.max_progress_rate || 4}
+                onChange={handleNumberChange('max_progress_rate')}
+              />
+            </div>
+
             <div className="setting-item">
               <label data-tooltip={t('settingsPage.advancedSettings.threadCount.tooltip')}>
                 {t('settingsPage.advancedSettings.threadCount.label')}
@@ -282,7 +389,7 @@ const SettingsPage: React.FC = () => {
                 type="number"
                 min="1"
                 max="32"
-                value={settings.thread_count || '16'}
+                value={settings.thread_count || 16}
                 onChange={handleNumberChange('thread_count')}
               />
             </div>
@@ -1,13 +1,13 @@
 export interface Settings {
   download_location?: string;
-  default_format?: string;
+  default_format?: 'mkv' | 'mp4' | 'ts';
   auto_start?: boolean;
-  concurrent_downloads?: string;
-  timeout?: string;
+  concurrent_downloads?: number;
+  timeout?: number;
   debug_mode?: boolean;
   tmp_dir?: string;
-  thread_count?: string;
-  download_retry_count?: string;
+  thread_count?: number;
+  download_retry_count?: number;
   check_segments_count?: boolean;
   binary_merge?: boolean;
   use_ffmpeg_concat_demuxer?: boolean;
@@ -20,8 +20,37 @@ export interface Settings {
   sub_only?: boolean;
   sub_format?: 'SRT' | 'VTT';
   auto_subtitle_fix?: boolean;
-  log_level?: string;
+  log_level?: 'DEBUG' | 'INFO' | 'WARN' | 'ERROR' | 'OFF';
   use_system_proxy?: boolean;
+  discovery_mode?: 'native' | 'meta_json';
+  selection_rules?: SelectionRules | null;
+  max_progress_rate?: number;
+}
+
+export interface VideoRule {
+  pick?: 'best' | 'worst';
+  max_height?: number | null;
+  min_height?: number | null;
+  codecs?: string[];
+}
+
+export interface TrackRule {
+  languages?: string[];
+  all?: boolean;
+  pick?: 'best' | 'worst';
+  codecs?: string[];
+  limit?: number | null;
+}
+
+export interface SelectionRules {
+  video?: VideoRule | null;
+  audio?: TrackRule | null;
+  subtitles?: TrackRule | null;
+}
+
+export interface ProfileList {
+  active_profile: string;
+  profiles: string[];
 }
 
 export interface MediaOptions {
@@ -29,13 +58,90 @@ export interface MediaOptions {
   description: string;
 }
 
+export interface VideoStream {
+  id: string;
+  group_id: string | null;
+  resolution: string | null;
+  bandwidth: number | null;
+  frame_rate: number | null;
+  codecs: string | null;
+  url: string | null;
+  audio_group: string | null;
+  subtitle_group: string | null;
+  period: number | null;
+  role: string | null;
+  encrypted: boolean;
+  segment_count: number | null;
+  duration: number | null;
+  encryption: string | null;
+}
+
+export interface AudioStream {
+  id: string;
+  group_id: string | null;
+  name: string | null;
+  language: string | null;
+  channels: string | null;
+  bandwidth: number | null;
+  codecs: string | null;
+  url: string | null;
+  period: number | null;
+  role: string | null;
+  encrypted: boolean;
+  segment_count: number | null;
+  duration: number | null;
+  encryption: string | null;
+}
+
+export interface SubtitleStream {
+  id: string;
+  group_id: string | null;
+  name: string | null;
+  language: string | null;
+  codecs: string | null;
+  url: string | null;
+  period: number | null;
+  role: string | null;
+  segment_count: number | null;
+  duration: number | null;
+}
+
+export interface DiscoveryError {
+  kind: 'network' | 'http_status' | 'parse' | 'binary_missing' | 'timeout' | 'cancelled' | 'other';
+  message: string;
+  status: number | null;
+  output: string | null;
+}
+
+export interface LogLine {
+  offset: number;
+  source: 'stdout' | 'stderr' | 'app';
+  timestamp: number;
+  text: string;
+}
+
+export interface LogChunk {
+  lines: LogLine[];
+  next_offset: number;
+  dropped: number;
+  path: string | null;
+}
+
+export interface StreamInfo {
+  video_streams: VideoStream[];
+  audio_streams: AudioStream[];
+  subtitle_streams: SubtitleStream[];
+  // Every video and audio stream needs a DRM license
+  drm_only: boolean;
+}
+
 export interface DownloadOptions {
   url: string;
   saveName: string;
   headers: Header[];
-  videoQuality: MediaOptions | undefined;
-  audioStream: MediaOptions | undefined;
-  subtitles: MediaOptions | undefined;
+  videoQuality: VideoStream | undefined;
+  audioStreams: AudioStream[];
+  subtitles: SubtitleStream[];
   autoMerge: boolean;
   fastStart: boolean;
   audioOnly: boolean;